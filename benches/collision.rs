@@ -0,0 +1,145 @@
+//! Times collision with more and more projectiles on screen, checking every
+//! enemy against every player beam against going through the `SpatialGrid`.
+//! Run with `cargo bench --bench collision [-- projectiles]`.
+
+use std::{hint::black_box, time::Instant};
+
+use bevy::{app::AppExit, prelude::*};
+use first_game::{
+    beam::{Beam, BeamType, Faction},
+    collision_core::SpatialGrid,
+    enemy_core::{EnemyCore, EnemyCoreBundle, EnemyType, SpawnEnemyEvent},
+    headless::{headless_app, start_playing},
+    hitbox::Hitbox,
+    level::Wave,
+    player::Player,
+};
+
+const ENEMIES: u32 = 100;
+// Fixed steps timed per run, after a few to settle in
+const WARMUP_FRAMES: u32 = 10;
+const FRAMES: u32 = 300;
+// Times each way of finding hits is run on the same enemies and beams
+const PASSES: u32 = 100;
+// Everything is scattered over the screen
+const AREA: Vec2 = Vec2::new(1200., 800.);
+
+/// Runs with up to `projectiles` on screen (8000 if not given), doubling from 500.
+/// Half are enemy fire, half are the player's beams, and none of them move or do any damage.
+fn main() {
+    // `cargo bench` passes `--bench`
+    let projectiles = std::env::args().skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(8000);
+    let mut count = 500.min(projectiles);
+    loop {
+        run(count);
+        if count >= projectiles {
+            break;
+        }
+        count = (count * 2).min(projectiles);
+    }
+}
+
+fn run(projectiles: u32) {
+    let mut app = headless_app();
+    start_playing(&mut app);
+
+    let mut rng = fastrand::Rng::with_seed(0);
+    let random_position = |rng: &mut fastrand::Rng| (Vec2::new(rng.f32(), rng.f32()) - 0.5) * AREA;
+    let world = &mut app.world;
+    for mut player in world.query::<&mut Player>().iter_mut(world) {
+        player.health = f32::MAX;
+    }
+    for _ in 0..ENEMIES {
+        let enemy_core = EnemyCore::builder().health(f32::MAX).shoot(false).build();
+        let position = random_position(&mut rng);
+        world.send_event(SpawnEnemyEvent((
+            EnemyCoreBundle { enemy_core },
+            EnemyType::Basic,
+            Transform::from_translation(position.extend(0.)),
+            Wave::One,
+        )));
+    }
+    // The player's beams as they were fired, before any of them hit something
+    let mut beams = vec![];
+    for i in 0..projectiles {
+        let transform = Transform::from_translation(random_position(&mut rng).extend(0.));
+        let (beam_type, faction) = if i % 2 == 0 {
+            (BeamType::FireBall, Faction::Enemy)
+        } else {
+            (BeamType::Wave, Faction::Player)
+        };
+        let mut beam = Beam::new(&beam_type, Vec2::Y).speed(0.).faction(faction);
+        beam.power = 0.;
+        beam.lifetime = f32::MAX;
+        if faction == Faction::Player {
+            beams.push((transform, beam_type.hitbox()));
+        }
+        world.spawn((transform, beam, beam_type.hitbox()));
+    }
+
+    for _ in 0..WARMUP_FRAMES {
+        app.update();
+    }
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        app.update();
+    }
+    let frame_ms = start.elapsed().as_secs_f64() * 1000. / FRAMES as f64;
+
+    // The enemies the game just ran with, and every beam it started with
+    let world = &mut app.world;
+    let enemies: Vec<(Transform, Hitbox)> = world.query_filtered::<(&Transform, &Hitbox), With<EnemyCore>>()
+        .iter(world)
+        .map(|(transform, hitbox)| (*transform, *hitbox))
+        .collect();
+    let (brute_force_ms, brute_force_hits) = time_passes(|| brute_force_hits(&enemies, &beams));
+    let (grid_ms, grid_hits) = time_passes(|| grid_hits(&enemies, &beams));
+    assert_eq!(brute_force_hits, grid_hits, "the grid missed hits");
+    info!(
+        "{} projectiles, {} enemies: {:.3} ms per frame, finding {} hits takes {:.3} ms checking every pair, {:.3} ms with the grid",
+        projectiles, enemies.len(), frame_ms, grid_hits, brute_force_ms, grid_ms,
+    );
+    app.world.send_event(AppExit);
+    app.update();
+}
+
+// Milliseconds per pass, and what the passes found
+fn time_passes(mut pass: impl FnMut() -> usize) -> (f64, usize) {
+    let mut hits = 0;
+    let start = Instant::now();
+    for _ in 0..PASSES {
+        hits = black_box(pass());
+    }
+    (start.elapsed().as_secs_f64() * 1000. / PASSES as f64, hits)
+}
+
+fn brute_force_hits(enemies: &[(Transform, Hitbox)], beams: &[(Transform, Hitbox)]) -> usize {
+    enemies.iter()
+        .map(|(transform, hitbox)| {
+            beams.iter()
+                .filter(|(beam_transform, beam_hitbox)| hitbox.intersects(transform, beam_hitbox, beam_transform))
+                .count()
+        })
+        .sum()
+}
+
+// Builds the grid too, as the game does every step. Beams go in under their index,
+// standing in for the entity the game looks them up by
+fn grid_hits(enemies: &[(Transform, Hitbox)], beams: &[(Transform, Hitbox)]) -> usize {
+    let mut grid = SpatialGrid::default();
+    for (index, (transform, hitbox)) in beams.iter().enumerate() {
+        grid.insert(Entity::from_raw(index as u32), hitbox.bounds(transform));
+    }
+    enemies.iter()
+        .map(|(transform, hitbox)| {
+            grid.query(hitbox.bounds(transform))
+                .filter(|entry| {
+                    let (beam_transform, beam_hitbox) = &beams[entry.entity.index() as usize];
+                    hitbox.intersects(transform, beam_hitbox, beam_transform)
+                })
+                .count()
+        })
+        .sum()
+}
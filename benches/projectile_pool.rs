@@ -0,0 +1,127 @@
+//! Times firing and retiring projectiles, spawning and despawning every one of
+//! them against going through the `ProjectilePool`.
+//! Run with `cargo bench --bench projectile_pool [-- projectiles]`.
+
+use std::{collections::VecDeque, time::Instant};
+
+use bevy::{app::AppExit, prelude::*};
+use first_game::{
+    beam::{Beam, BeamType},
+    headless::{headless_app, start_playing},
+    player::Player,
+    projectile_pool::{Pooled, ProjectilePool},
+    GameState,
+};
+
+// Fixed steps timed per run, after a few to settle in
+const WARMUP_FRAMES: u32 = 10;
+const FRAMES: u32 = 300;
+// Everything is scattered over the screen
+const AREA: Vec2 = Vec2::new(1200., 800.);
+// Each projectile lasts this many fixed steps before being replaced
+const CHURN_STEPS: u32 = 16;
+
+/// Runs with up to `projectiles` alive at once (8000 if not given), doubling from 500.
+fn main() {
+    // `cargo bench` passes `--bench`
+    let projectiles = std::env::args().skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(8000);
+    let mut count = 500.min(projectiles);
+    loop {
+        let spawned_ms = run_churn(count, false);
+        let pooled_ms = run_churn(count, true);
+        info!(
+            "{} projectiles: {:.3} ms per frame spawning them, {:.3} ms pooled",
+            count, spawned_ms, pooled_ms,
+        );
+        if count >= projectiles {
+            break;
+        }
+        count = (count * 2).min(projectiles);
+    }
+}
+
+#[derive(Resource)]
+struct Churn {
+    pooled: bool,
+    per_step: u32,
+    live: VecDeque<Entity>,
+    fired: u32,
+}
+
+// Retires the oldest projectiles and fires as many new ones, like a busy wave would
+fn churn(
+    mut churn: ResMut<Churn>,
+    mut pool: ResMut<ProjectilePool>,
+    pooled_query: Query<&Pooled>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let churn = churn.as_mut();
+    if churn.live.len() as u32 >= churn.per_step * CHURN_STEPS {
+        for entity in churn.live.drain(..churn.per_step as usize) {
+            if churn.pooled {
+                pool.release(&mut commands, entity, pooled_query.get(entity).ok());
+            } else if let Some(mut entity_commands) = commands.get_entity(entity) {
+                // Unless it already hit one of the level's enemies
+                entity_commands.despawn();
+            }
+        }
+    }
+    let beam_type = BeamType::FireBall;
+    for _ in 0..churn.per_step {
+        // Spread over the screen, away from where anything gets culled
+        let i = churn.fired;
+        churn.fired += 1;
+        let position = Vec2::new((i % 97) as f32 / 97., (i % 89) as f32 / 89.) - 0.5;
+        let transform = Transform::from_translation((position * AREA).extend(10.));
+        let beam = Beam::new(&beam_type, Vec2::Y).speed(0.);
+        let entity = if churn.pooled {
+            pool.acquire(&mut commands, &asset_server, &beam_type, transform).insert(beam).id()
+        } else {
+            commands.spawn((
+                SpriteBundle {
+                    texture: asset_server.load(beam_type.texture_path()),
+                    transform,
+                    ..default()
+                },
+                beam,
+                beam_type.hitbox(),
+            )).id()
+        };
+        churn.live.push_back(entity);
+    }
+}
+
+fn run_churn(projectiles: u32, pooled: bool) -> f64 {
+    let mut app = headless_app();
+    app.insert_resource(Churn {
+        pooled,
+        per_step: (projectiles / CHURN_STEPS).max(1),
+        live: VecDeque::new(),
+        fired: 0,
+    });
+    app.add_systems(FixedUpdate, churn.run_if(in_state(GameState::Playing)));
+    start_playing(&mut app);
+    for mut player in app.world.query::<&mut Player>().iter_mut(&mut app.world) {
+        player.health = f32::MAX;
+    }
+
+    // Long enough for the pool to grow to what it needs
+    for _ in 0..WARMUP_FRAMES + CHURN_STEPS * 2 {
+        app.update();
+    }
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        app.update();
+    }
+    let frame_ms = start.elapsed().as_secs_f64() * 1000. / FRAMES as f64;
+    if pooled {
+        let stats = app.world.resource::<ProjectilePool>().stats(&BeamType::FireBall);
+        info!("FireBall pool: {} spawned, {} in use at most", stats.spawned, stats.peak);
+    }
+    app.world.send_event(AppExit);
+    app.update();
+    frame_ms
+}
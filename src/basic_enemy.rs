@@ -1,57 +1,49 @@
-use bevy::{app::{App, Plugin}, math::bounding::{Aabb2d, BoundingCircle, IntersectsVolume}, prelude::*};
+use bevy::{app::{App, Plugin}, prelude::*};
 
-use crate::{basic_enemy_move_patterns::EnemyMovePattern, beam::{Beam, BeamType, ShootType}, collision_core::CollisionEvent, enemy_core::{EnemyCore, EnemyState, ShootTimer}, explosion_core::ExplosionEvent, level::Wave, player::Player, GameState};
+use crate::{basic_enemy_move_patterns::{EnemyMovePattern, StartShootGoStage}, beam::{Beam, BeamType, Faction, ShootType}, boss::{Boss, BossDefeatedEvent}, collision_core::{BroadphaseSet, CollisionEvent, CollisionGrid}, enemy_core::{EnemyCore, EnemyKilledEvent, EnemyState, EnemyType, ShootTimer}, explosion_core::ExplosionEvent, hitbox::Hitbox, level::Wave, player::Player, projectile_pool::{Pooled, ProjectilePool}, replay::GameRng, rose_gunner::RoseGunner, GameState};
 
 const ENEMY_SPEED: f32 = 400.;
+// Basic enemies turn around here
+const BASIC_TURN_X: f32 = 600.;
+// EnterShoot enemies fly down to here and stay
+const ENTER_SHOOT_STOP_Y: f32 = 200.;
+// Down enemies are gone once they get below this
+const DOWN_DESPAWN_Y: f32 = -100.;
 
 pub struct BasicEnemyPlugin;
 impl Plugin for BasicEnemyPlugin {
     fn build(&self, app: &mut App) {
        app
        .insert_resource(ShootTimer(Timer::from_seconds(2.0, TimerMode::Repeating)))
-       .add_systems(Update, 
-        (move_enemy, enemy_fire, animate_beams, check_collision)
+       .add_systems(FixedUpdate, 
+        (move_enemy, enemy_fire.before(BroadphaseSet), check_collision.after(BroadphaseSet))
                 .chain()
                 .run_if(in_state(GameState::Playing))
         )
+       .configure_sets(
+            FixedUpdate,
+            EnemyBehaviorSet
+                .after(move_enemy)
+                .before(enemy_fire)
+                .run_if(in_state(GameState::Playing))
+        )
        ;
 
     }
 }
 
+/// Behavior specific to one `EnemyType` (see ghost_orchid.rs) goes in here.
+/// It runs after the shared movement and before enemies fire or get hit.
+#[derive(SystemSet, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct EnemyBehaviorSet;
+
+/// Enemies with this can not be hit by the player's beams, and hold their fire.
 #[derive(Component)]
-pub struct BasicEnemy {
-    pub x_direction: f32,
-    pub y_direction: f32,
-    health: f32,
-    move_pattern: EnemyMovePattern,
-    state: EnemyState,
-    shoot: bool,
-}
+pub struct Intangible;
+
+/// Enemies with this hold their fire, but can still be hit.
 #[derive(Component)]
-pub struct EnemyFire {
-    pub power: f32,
-}
-impl BasicEnemy {
-    pub fn new(move_pattern: EnemyMovePattern) -> Self {
-        BasicEnemy { 
-            state: EnemyState::Active, 
-            x_direction : 1.,
-            y_direction : 0., 
-            health: 100.,
-            move_pattern: move_pattern,
-            shoot: true,
-        }
-    }
-    pub fn shoot(mut self, shoot:bool) -> Self {
-        self.shoot = shoot;
-        self
-    }
-    pub fn health(mut self, health: f32) -> Self {
-        self.health = health;
-        self
-    }
-}
+pub struct HoldFire;
 
 fn move_enemy(
     mut query: Query<(&mut EnemyCore, &mut Transform, Entity)>,
@@ -67,9 +59,9 @@ fn move_enemy(
         match enemy.move_pattern {
             EnemyMovePattern::Basic => {
                 //simply flip direction depending on bounds
-                if transform.translation.x >= 600. {
+                if transform.translation.x >= BASIC_TURN_X {
                     enemy.x_direction = -1.
-                } else if transform.translation.x <= -600. {
+                } else if transform.translation.x <= -BASIC_TURN_X {
                     enemy.x_direction = 1.
                 }
                 //now move enemy
@@ -92,15 +84,45 @@ fn move_enemy(
                     transform.translation.x + (ENEMY_SPEED / 2. * enemy.x_direction) * time.delta_seconds();
                 transform.translation.x = new_x_pos;
 
-                if new_y_pos < -100. {
+                if new_y_pos < DOWN_DESPAWN_Y {
                     commands.entity(entity).despawn();
                 }
             }
             EnemyMovePattern::StartShootGo => {
-
+                let position = transform.translation.truncate();
+                let step = ENEMY_SPEED * time.delta_seconds();
+                let shoot = enemy.shoot;
+                let plan = &mut enemy.start_shoot_go;
+                match plan.stage {
+                    StartShootGoStage::Entering => {
+                        let to_stop = plan.stop_position - position;
+                        if to_stop.length() <= step {
+                            transform.translation = plan.stop_position.extend(transform.translation.z);
+                            plan.stage = StartShootGoStage::Shooting;
+                        } else {
+                            transform.translation += (to_stop.normalize() * step).extend(0.);
+                        }
+                    }
+                    // enemy_fire counts the volleys
+                    StartShootGoStage::Shooting => {
+                        if !shoot || plan.volleys_fired >= plan.volleys {
+                            plan.stage = StartShootGoStage::Leaving;
+                        }
+                    }
+                    // Culled once it is off screen, see collision_core.rs
+                    StartShootGoStage::Leaving => {
+                        transform.translation += (plan.exit_direction.normalize_or_zero() * step).extend(0.);
+                    }
+                }
+            },
+            EnemyMovePattern::Path => {
+                match enemy.path.advance(transform.translation.truncate(), time.delta_seconds()) {
+                    Some(position) => transform.translation = position.extend(transform.translation.z),
+                    None => commands.entity(entity).despawn(),
+                }
             },
             EnemyMovePattern::EnterShoot => {
-                if transform.translation.y > 200. {
+                if transform.translation.y > ENTER_SHOOT_STOP_Y {
                     enemy.x_direction = -1.;
                     let new_y_pos = transform.translation.y + (ENEMY_SPEED * enemy.y_direction) * time.delta_seconds();
                     transform.translation.y = new_y_pos;
@@ -113,106 +135,142 @@ fn move_enemy(
     }
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn enemy_fire(
-    mut query: Query<(&mut EnemyCore, &mut Transform)>,
+    mut query: Query<(&mut EnemyCore, &Transform, Option<&RoseGunner>), (Without<Intangible>, Without<HoldFire>)>,
+    player: Query<&Transform, (With<Player>, Without<EnemyCore>)>,
     time: Res<Time>,
     mut timer: ResMut<ShootTimer>,
+    mut rng: ResMut<GameRng>,
+    mut pool: ResMut<ProjectilePool>,
     mut commands: Commands,
     asset_server: Res<AssetServer>
 ) {
-    for (mut enemy, transform) in query.iter_mut() {
-        match enemy.shoot_type.to_owned() {
+    let player_position = player.get_single().ok().map(|p| p.translation.truncate());
+    for (mut enemy, transform, gunner) in query.iter_mut() {
+        // Lets the shoot type and its timer be borrowed together
+        let enemy = enemy.as_mut();
+        if !enemy.shoot {
+            continue;
+        }
+        let start_shoot_go = matches!(enemy.move_pattern, EnemyMovePattern::StartShootGo);
+        // Only fires from its stop position
+        if start_shoot_go && enemy.start_shoot_go.stage != StartShootGoStage::Shooting {
+            continue;
+        }
+        let fired = match &mut enemy.shoot_type {
             crate::beam::ShootType::Basic => {
                 // Shoot every N seconds
                 if timer.0.tick(time.delta()).just_finished() {
-                    commands.spawn((
-                        SpriteBundle {
-                            texture: asset_server.load("beam_basic.png"),
-                            transform: Transform::from_xyz(
-                                transform.translation.x,
-                                transform.translation.y,
-                                0.),
-                            ..default()
-                        },
-                        BasicBeam {speed: BEAM_SPEED,  y : -1., x: 0. },
-                        EnemyFire { power: 20. },
-                    ));
+                    let spawn_transform = Transform::from_xyz(transform.translation.x, transform.translation.y, 0.);
+                    pool.acquire(&mut commands, &asset_server, &BeamType::Proton, spawn_transform).insert(
+                        Beam::new(&BeamType::Proton, Vec2::NEG_Y).faction(Faction::Enemy),
+                    );
+                    true
+                } else {
+                    false
                 }
             },
-            ShootType::TestHell(mut shoot_pattern) => {
+            ShootType::TestHell(shoot_pattern) => {
+                shoot_pattern.tick(time.delta());
                 if enemy.shoot_timer.0.tick(time.delta()).just_finished() {
-                    for beam in shoot_pattern.beam.iter_mut() {
-                        let texture_path = match beam.beam_type {
-                            BeamType::FireBall => {
-                                "beam_fireball.png"
-                            }
-                            _ => {
-                                "beam_basic.png"
-                            }
-                        };
-                        commands.spawn((
-                            SpriteBundle {
-                                texture: asset_server.load(texture_path),
-                                transform: Transform::from_xyz(transform.translation.x, transform.translation.y, 10.),
-                                ..Default::default()
-                            },
-                            BasicBeam {speed: beam.speed, y: beam.direction.y, x: beam.direction.x },
-                            EnemyFire { power: beam.power }
-                        ));
-                    }
+                    let volley = shoot_pattern.volley(&mut rng.0);
+                    spawn_volley(&mut commands, &mut pool, &asset_server, &volley, transform, Vec2::X);
+                    !volley.is_empty()
+                } else {
+                    false
+                }
+            },
+            ShootType::Aimed(shoot_pattern) => {
+                shoot_pattern.tick(time.delta());
+                if enemy.shoot_timer.0.tick(time.delta()).just_finished() {
+                    // Gunners fire where they have turned to, so they can be outrun.
+                    // Anything else aims at the player, or straight down with no one to aim at
+                    let aim = match gunner {
+                        Some(gunner) => Vec2::from_angle(gunner.facing()),
+                        None => player_position
+                            .map(|position| position - transform.translation.truncate())
+                            .filter(|offset| *offset != Vec2::ZERO)
+                            .map_or(Vec2::X, |offset| Vec2::from_angle(Vec2::NEG_Y.angle_between(offset))),
+                    };
+                    let volley = shoot_pattern.volley(&mut rng.0);
+                    spawn_volley(&mut commands, &mut pool, &asset_server, &volley, transform, aim);
+                    !volley.is_empty()
+                } else {
+                    false
                 }
             },
+        };
+        if fired && start_shoot_go {
+            enemy.start_shoot_go.volleys_fired += 1;
         }
     }
 }
 
-#[derive(Component)]
-pub struct BasicBeam {
-    x: f32,
-    y: f32,
-    speed: f32,
-}
-pub const BEAM_SPEED: f32 = 250.;
-fn animate_beams(
-    mut query: Query<(&mut BasicBeam, &mut Transform)>,
-    time: Res<Time>,
+/// Fires a volley of beams at once, with their directions turned by `rotation`.
+fn spawn_volley(
+    commands: &mut Commands,
+    pool: &mut ProjectilePool,
+    asset_server: &AssetServer,
+    volley: &[Beam],
+    transform: &Transform,
+    rotation: Vec2,
 ) {
-    for (beam, mut transform) in query.iter_mut() {
-        let new_y = transform.translation.y + (beam.y * beam.speed * time.delta_seconds());
-        transform.translation.y = new_y;
-        let new_x = transform.translation.x + (beam.x * beam.speed * time.delta_seconds());
-        transform.translation.x = new_x;
+    for beam in volley.iter() {
+        let spawn_transform = Transform::from_xyz(transform.translation.x, transform.translation.y, 10.);
+        let fired = Beam {
+            direction: rotation.rotate(beam.direction),
+            faction: Faction::Enemy,
+            ..beam.clone()
+        };
+        pool.acquire(commands, asset_server, &beam.beam_type, spawn_transform).insert(fired);
     }
 }
 
-fn check_collision(
-    mut enemy_query: Query<(&Wave, Entity, &Transform, &mut EnemyCore), With<EnemyCore>>,
-    mut beam_query: Query<(Entity, &Transform, &Beam), With<Beam>>,
+pub const BEAM_SPEED: f32 = 250.;
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn check_collision(
+    mut enemy_query: Query<(&Wave, Entity, &Transform, &Hitbox, &mut EnemyCore, &EnemyType, Option<&Boss>, Has<Intangible>)>,
+    beam_query: Query<(&Beam, &Transform, &Hitbox, Option<&Pooled>)>,
+    grid: Res<CollisionGrid>,
+    mut pool: ResMut<ProjectilePool>,
     mut collision_events: EventWriter<CollisionEvent>,
     mut explosion_events: EventWriter<ExplosionEvent>,
+    mut boss_defeated_events: EventWriter<BossDefeatedEvent>,
+    mut enemy_killed_events: EventWriter<EnemyKilledEvent>,
     mut commands : Commands,
 ) {
 
-    for (wave, e_entity, e_transform, mut e_enemy) in enemy_query.iter_mut() {
+    for (_wave, e_entity, e_transform, e_hitbox, mut e_enemy, enemy_type, boss, intangible) in enemy_query.iter_mut() {
         match e_enemy.state {
             EnemyState::Active => {
-                let ecircle = 
-                    BoundingCircle::new(
-                        e_transform.translation.truncate(),
-                        10.);
-                for (b_entity, b_transform, beam) in beam_query.iter_mut()  {
-                    let b_box = 
-                        Aabb2d::new(b_transform.translation.truncate(), b_transform.scale.truncate() / 2.);
-                    if ecircle.intersects(&b_box) {
-                        collision_events.send(CollisionEvent(e_entity));
-                        e_enemy.health -= beam.power;
-                        commands.entity(b_entity).despawn();
+                // Beams pass straight through intangible enemies
+                if !intangible {
+                    for entry in grid.fired_by(Faction::Player).query(e_hitbox.bounds(e_transform)) {
+                        let Ok((beam, b_transform, b_hitbox, pooled)) = beam_query.get(entry.entity) else {
+                            continue;
+                        };
+                        if e_hitbox.intersects(e_transform, b_hitbox, b_transform) {
+                            collision_events.send(CollisionEvent(e_entity));
+                            e_enemy.health -= beam.power;
+                            pool.release(&mut commands, entry.entity, pooled);
+                        }
                     }
                 }
                 if e_enemy.health < 1. {
                     e_enemy.state = EnemyState::Dead;
                     let explosion_transform = Transform::from_xyz(e_transform.translation.x, e_transform.translation.y, 2.);
                     explosion_events.send(ExplosionEvent(explosion_transform));
+                    enemy_killed_events.send(EnemyKilledEvent { enemy_type: *enemy_type });
+                    if boss.is_some() {
+                        // A few extra explosions around a boss
+                        for offset in [Vec2::new(-30., 20.), Vec2::new(25., -15.), Vec2::new(10., 35.)] {
+                            let offset_transform = explosion_transform.with_translation(explosion_transform.translation + offset.extend(0.));
+                            explosion_events.send(ExplosionEvent(offset_transform));
+                        }
+                        boss_defeated_events.send(BossDefeatedEvent);
+                    }
                     commands.entity(e_entity).despawn();
                 }
             }
@@ -1,21 +1,43 @@
-use bevy::{prelude::*};
+use bevy::math::Vec2;
+use serde::Deserialize;
 
-use crate::basic_enemy::BasicEnemy;
-
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Deserialize)]
 pub enum EnemyMovePattern {
     #[default]
     Basic,
     Down,
     EnterShoot,
     StartShootGo,
+    // Follows the enemy's EnemyPath, see enemy_path.rs
+    Path,
 }
 
-pub fn basic_move (transform: Mut<Transform>, mut enemy: Mut<BasicEnemy>) {
-    //simply flip direction depending on bounds
-    if transform.translation.x >= 600. {
-        enemy.x_direction = -1.
-    } else if transform.translation.x <= -600. {
-        enemy.x_direction = 1.
+/// Where `EnemyMovePattern::StartShootGo` stops, how long it stays and where it goes after.
+#[derive(Clone, Debug)]
+pub struct StartShootGo {
+    pub stop_position: Vec2,
+    // Volleys fired from the stop position before leaving
+    pub volleys: u32,
+    pub exit_direction: Vec2,
+    pub volleys_fired: u32,
+    pub stage: StartShootGoStage,
+}
+impl Default for StartShootGo {
+    fn default() -> Self {
+        StartShootGo {
+            stop_position: Vec2::new(0., 250.),
+            volleys: 3,
+            exit_direction: Vec2::new(0., 1.),
+            volleys_fired: 0,
+            stage: StartShootGoStage::Entering,
+        }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StartShootGoStage {
+    #[default]
+    Entering,
+    Shooting,
+    Leaving,
+}
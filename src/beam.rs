@@ -1,43 +1,157 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
+use serde::Deserialize;
+use aimed_burst::AimedBurst;
 use flat_spray::FlatSpray;
 use line_spray::LineSpray;
 
-use crate::{basic_enemy::BEAM_SPEED, shoot_patterns::*, GameState};
+use crate::{basic_enemy::BEAM_SPEED, collision_core::BroadphaseSet, hitbox::Hitbox, level::WaveState, projectile_pool::{Pooled, ProjectilePool}, shoot_patterns::*, GameState};
 
 pub struct BeamPlugin;
 impl Plugin for BeamPlugin {
     fn build(&self, app: &mut App) {
        app
-       .add_systems(Update, (update_beam).run_if(in_state(GameState::Playing)));
+       .add_systems(FixedUpdate, (update_beam).before(BroadphaseSet).run_if(in_state(GameState::Playing)))
+       .add_systems(OnExit(GameState::GameOver), clear_beams)
+       .add_systems(OnEnter(GameState::LevelComplete), clear_beams)
+       .add_systems(OnTransition {from: WaveState::Active, to: WaveState::Initialized}, clear_beams);
     }
 }
 
+/// Every projectile, whoever fired it. Moved and timed out by `update_beam`,
+/// and checked against whatever its `faction` can hit.
+// Added and removed as projectiles go in and out of the pool, which is cheap in a sparse set
 #[derive(Clone, Component, Debug)]
+#[component(storage = "SparseSet")]
 pub struct Beam {
     pub beam_type: BeamType,
     pub lifetime: f32,
     pub speed: f32,
     pub direction: Vec2,
     pub power: f32,
+    pub faction: Faction,
+}
+
+/// Who fired a beam. Player beams hit enemies, enemy beams hit the player.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Faction {
+    #[default]
+    Player,
+    Enemy,
 }
 
 #[derive(Clone, Debug, Default)]
 pub enum ShootType {
     #[default]
     Basic,
-    TestHell(ShootPattern)
+    TestHell(ShootPattern),
+    // Beam directions are relative to straight down, and get turned toward the player when fired
+    Aimed(ShootPattern),
 }
+/// One volley's worth of beams, fired together. The generators in
+/// shoot_patterns/generators.rs build these from a count, speed and angle.
+/// A pattern lives on its enemy, so it can change from one volley to the next.
 #[derive(Clone, Debug)]
 pub struct ShootPattern {
     pub beam: Vec<Beam>,
-    pub timer: Timer
+    // One cycle of the pattern, ticked while the enemy can fire
+    pub timer: Timer,
+    // The last seconds of each cycle are spent not firing
+    pub rest: f32,
+    // Radians the whole volley turns by after each one is fired, for spirals
+    pub spin: f32,
+    // Up to this many radians either way, picked per beam per volley
+    pub scatter: f32,
+    // Fired in turn instead of `beam` when there are any
+    pub alternates: Vec<ShootPattern>,
+    // How far the volleys have spun so far
+    turned: f32,
+    // Which alternate goes next
+    next: usize,
 }
 impl ShootPattern {
+    pub fn new(beam: Vec<Beam>) -> ShootPattern {
+        ShootPattern {
+            beam,
+            timer: Timer::from_seconds(0.25, TimerMode::Repeating),
+            rest: 0.,
+            spin: 0.,
+            scatter: 0.,
+            alternates: vec![],
+            turned: 0.,
+            next: 0,
+        }
+    }
+
+    /// Takes turns firing each of `patterns`, one per volley.
+    pub fn alternate(patterns: Vec<ShootPattern>) -> ShootPattern {
+        ShootPattern {
+            alternates: patterns,
+            ..ShootPattern::new(vec![])
+        }
+    }
+
+    pub fn spin(mut self, spin: f32) -> Self {
+        self.spin = spin;
+        self
+    }
+
+    pub fn scatter(mut self, scatter: f32) -> Self {
+        self.scatter = scatter;
+        self
+    }
+
+    /// Fires for `firing` seconds, then holds off for `resting` seconds, over and over.
+    pub fn bursts(mut self, firing: f32, resting: f32) -> Self {
+        self.timer = Timer::from_seconds(firing + resting, TimerMode::Repeating);
+        self.rest = resting;
+        self
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        self.timer.tick(delta);
+        for alternate in self.alternates.iter_mut() {
+            alternate.tick(delta);
+        }
+    }
+
+    fn resting(&self) -> bool {
+        self.rest > 0. && self.timer.remaining_secs() <= self.rest
+    }
+
+    /// The beams to fire this time, after spin and scatter are applied.
+    /// Empty while the pattern is resting.
+    pub fn volley(&mut self, rng: &mut fastrand::Rng) -> Vec<Beam> {
+        if self.resting() {
+            return vec![];
+        }
+        let beams = if self.alternates.is_empty() {
+            self.beam.clone()
+        } else {
+            let beams = self.alternates[self.next].volley(rng);
+            self.next = (self.next + 1) % self.alternates.len();
+            beams
+        };
+        let volley = beams.into_iter()
+            .map(|mut beam| {
+                let jitter = if self.scatter > 0. { (rng.f32() * 2. - 1.) * self.scatter } else { 0. };
+                beam.direction = Vec2::from_angle(self.turned + jitter).rotate(beam.direction);
+                beam
+            })
+            .collect();
+        self.turned = (self.turned + self.spin) % std::f32::consts::TAU;
+        volley
+    }
+
     pub fn flat_spray() -> ShootPattern {
-        FlatSpray::new()
+        FlatSpray::pattern()
     }
     pub fn line_spray() -> ShootPattern {
-        LineSpray::new()
+        LineSpray::pattern()
+    }
+    pub fn aimed_burst() -> ShootPattern {
+        AimedBurst::pattern()
     }
 }
 
@@ -49,8 +163,9 @@ impl Beam {
                     beam_type: beam_type.clone(),
                     lifetime: 4.,
                     speed: BEAM_SPEED,
-                    direction: direction,
+                    direction,
                     power: 20.,
+                    faction: Faction::Player,
                 }
             }
             BeamType::Laser => {
@@ -58,8 +173,9 @@ impl Beam {
                     beam_type: beam_type.clone(),
                     lifetime: 4.,
                     speed: BEAM_SPEED,
-                    direction: direction,
+                    direction,
                     power: 30.,
+                    faction: Faction::Player,
                 }
             }
             BeamType::Wave => {
@@ -67,8 +183,9 @@ impl Beam {
                     beam_type: beam_type.clone(),
                     lifetime: 4.,
                     speed: BEAM_SPEED + 300.,
-                    direction: direction,
+                    direction,
                     power: 50.,
+                    faction: Faction::Player,
                 }
             }
             BeamType::PlasmaOrb => {
@@ -76,8 +193,9 @@ impl Beam {
                     beam_type: beam_type.clone(),
                     lifetime: 4.,
                     speed: BEAM_SPEED,
-                    direction: direction,
+                    direction,
                     power: 50.,
+                    faction: Faction::Player,
                 }
             }
             BeamType::FireBall => {
@@ -85,8 +203,9 @@ impl Beam {
                     beam_type: beam_type.clone(),
                     lifetime: 4.,
                     speed: 600.,
-                    direction: direction,
+                    direction,
                     power: 10.,
+                    faction: Faction::Player,
                 }
             }
         }
@@ -96,29 +215,61 @@ impl Beam {
         self.speed = speed;
         self
     }
+
+    pub fn faction(mut self, faction: Faction) -> Self {
+        self.faction = faction;
+        self
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
 pub enum BeamType {
     PlasmaOrb,
     Proton,
     Laser,
     Wave,
+    // What enemies fire unless told otherwise
+    #[default]
     FireBall,
 }
+impl BeamType {
+    pub const ALL: [BeamType; 5] = [BeamType::PlasmaOrb, BeamType::Proton, BeamType::Laser, BeamType::Wave, BeamType::FireBall];
+
+    /// Sized from the sprite each type is drawn with.
+    pub fn hitbox(&self) -> Hitbox {
+        match self {
+            BeamType::FireBall => Hitbox::circle_for_sprite(Vec2::new(13., 13.), 0.8),
+            BeamType::Wave => Hitbox::capsule_for_sprite(Vec2::new(30., 10.), 1.),
+            BeamType::PlasmaOrb => Hitbox::capsule_for_sprite(Vec2::new(18., 9.), 1.),
+            // Drawn with beam_basic.png
+            BeamType::Proton | BeamType::Laser => Hitbox::aabb_for_sprite(Vec2::new(21., 27.), 0.8),
+        }
+    }
+}
 
 
 pub fn update_beam(
-    mut beam_query: Query<(&mut Beam, &mut Transform, Entity)>,
+    mut beam_query: Query<(&mut Beam, &mut Transform, Entity, Option<&Pooled>)>,
     time:Res<Time>,
+    mut pool: ResMut<ProjectilePool>,
     mut commands : Commands) {
-        for (mut beam, mut transform, entity) in beam_query.iter_mut() {
+        for (mut beam, mut transform, entity, pooled) in beam_query.iter_mut() {
             beam.lifetime -= time.delta_seconds();
             let moving = beam.speed * beam.direction * time.delta_seconds();
             transform.translation += Vec3::new(moving.x,moving.y,0.);
             if beam.lifetime <=0.
             {
-                commands.entity(entity).despawn();
+                pool.release(&mut commands, entity, pooled);
             }
         }
 }
+
+fn clear_beams(
+    beam_query: Query<(Entity, Option<&Pooled>), With<Beam>>,
+    mut pool: ResMut<ProjectilePool>,
+    mut commands: Commands,
+) {
+    for (entity, pooled) in beam_query.iter() {
+        pool.release(&mut commands, entity, pooled);
+    }
+}
@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+
+use crate::enemy_core::EnemyCore;
+
+pub struct BossPlugin;
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_event::<BossDefeatedEvent>()
+        .add_systems(Update, (spawn_health_bar, update_health_bar, despawn_health_bar).chain())
+        ;
+    }
+}
+
+// Bosses reuse a regular enemy sprite, just bigger
+pub const BOSS_SCALE: f32 = 3.;
+const HEALTH_BAR_COLOR: Color = Color::rgb(0.85, 0.1, 0.3);
+
+/// Marks the enemy spawned as `EnemyType::Boss`. Its phases are regular `EnemyPhase`s on its `EnemyCore`.
+#[derive(Component, Debug)]
+pub struct Boss {
+    pub max_health: f32,
+}
+
+/// Sent when a boss's health runs out. Finishes the level.
+#[derive(Event)]
+pub struct BossDefeatedEvent;
+
+#[derive(Component)]
+struct BossHealthBar(Entity);
+
+#[derive(Component)]
+struct BossHealthFill;
+
+fn spawn_health_bar(
+    mut commands: Commands,
+    boss_query: Query<Entity, Added<Boss>>,
+) {
+    for boss in boss_query.iter() {
+        commands.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(16.),
+                    left: Val::Percent(25.),
+                    width: Val::Percent(50.),
+                    height: Val::Px(10.),
+                    ..default()
+                },
+                background_color: Color::rgba(1., 1., 1., 0.2).into(),
+                ..default()
+            },
+            BossHealthBar(boss),
+        )).with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    background_color: HEALTH_BAR_COLOR.into(),
+                    ..default()
+                },
+                BossHealthFill,
+            ));
+        });
+    }
+}
+
+fn update_health_bar(
+    boss_query: Query<(&EnemyCore, &Boss), Changed<EnemyCore>>,
+    bar_query: Query<(&BossHealthBar, &Children)>,
+    mut fill_query: Query<&mut Style, With<BossHealthFill>>,
+) {
+    for (bar, children) in bar_query.iter() {
+        if let Ok((enemy, boss)) = boss_query.get(bar.0) {
+            let percent = (enemy.health / boss.max_health).clamp(0., 1.) * 100.;
+            for child in children.iter() {
+                if let Ok(mut style) = fill_query.get_mut(*child) {
+                    style.width = Val::Percent(percent);
+                }
+            }
+        }
+    }
+}
+
+fn despawn_health_bar(
+    mut commands: Commands,
+    bar_query: Query<(&BossHealthBar, Entity)>,
+    boss_query: Query<(), With<Boss>>,
+) {
+    for (bar, entity) in bar_query.iter() {
+        if boss_query.get(bar.0).is_err() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -3,15 +3,16 @@ use bevy::{
     prelude::*,
 };
 
-use crate::GameLevel;
+use crate::GameState;
 pub struct GameCameraPlugin;
 
 impl Plugin for GameCameraPlugin {
     fn build(&self, app: &mut App) {
         app
         .add_event::<CameraShakeEvent>()
-        .add_systems(OnEnter(GameLevel::SpaceOne), setup_camera)
-        .add_systems(FixedUpdate, (shake_camera_event, shake_camera).chain())
+        // Menus are drawn with it too, so it is there from the start
+        .add_systems(Startup, setup_camera)
+        .add_systems(FixedUpdate, (shake_camera_event, shake_camera).chain().run_if(not(in_state(GameState::Paused))))
         ;
     }
 }
@@ -1,6 +1,6 @@
-use bevy::{math::bounding::{Aabb2d, BoundingCircle, IntersectsVolume}, prelude::*};
+use bevy::prelude::*;
 
-use crate::{beam::{Beam, BeamType}, player::{Player, PlayerState, WeaponType}, GameState};
+use crate::{beam::{Beam, BeamType}, hitbox::Hitbox, player::{Player, PlayerState, WeaponType}, player_input::PlayerInput, projectile_pool::ProjectilePool, GameState};
 
 pub struct CanonPlugin;
 
@@ -8,8 +8,9 @@ impl Plugin for CanonPlugin {
     fn build(&self, app: &mut App) {
         app
         .add_systems(OnEnter(PlayerState::Spawned), setup)
+        .add_systems(OnExit(GameState::GameOver), despawn_canons)
         .add_systems(
-            Update,
+            FixedUpdate,
              (move_canon, fire_canon)
                         .chain()
                         .run_if(in_state(GameState::Playing))
@@ -29,7 +30,6 @@ impl Plugin for CanonPlugin {
  */
 #[derive(Component, Debug)]
 struct Canon {
-    level: u8, // The nth order of the canon.
     lockout_time: f32,
     needs_cooldown: bool,
     position: Position,
@@ -40,12 +40,11 @@ pub enum Position {
     Right
 }
 impl Canon {
-    fn new(level: u8, position: Position,) -> Self {
+    fn new(position: Position) -> Self {
         Canon {
-            level: level,
             lockout_time: 0.35,
             needs_cooldown: false,
-            position: position,
+            position,
         }
     }
 }
@@ -75,13 +74,13 @@ fn setup(
         let layout = TextureAtlasLayout::from_grid(Vec2::new(21., CANON_HEIGHT), 4, 1, None, None);
         let texture_atlas_layout = texture_atlas_layouts.add(layout);
         let animation_indices = AnimationIndices { first: 0, last: 3 };
-        let canon = Canon::new(0, Position::Left); 
+        let canon = Canon::new(Position::Left); 
         let canon_lockout = canon.lockout_time;
         let mut animation_timer = AnimationTimer(Timer::from_seconds(CANON_ANIMATION_SPEED, TimerMode::Repeating));
         animation_timer.0.pause();
         commands.spawn((
             SpriteSheetBundle {
-                texture: texture,
+                texture,
                 atlas: TextureAtlas {
                     layout: texture_atlas_layout,
                     index: animation_indices.first,
@@ -90,6 +89,7 @@ fn setup(
                 ..default()
             },
             canon,
+            Hitbox::aabb_for_sprite(Vec2::new(21., CANON_HEIGHT), 1.),
             animation_indices,
             ShootTimer(Timer::from_seconds(canon_lockout, TimerMode::Once)),
             animation_timer,
@@ -100,13 +100,13 @@ fn setup(
         let layout = TextureAtlasLayout::from_grid(Vec2::new(21., CANON_HEIGHT), 4, 1, None, None);
         let texture_atlas_layout = texture_atlas_layouts.add(layout);
         let animation_indices = AnimationIndices { first: 0, last: 3 };
-        let canon = Canon::new(0, Position::Right); 
+        let canon = Canon::new(Position::Right); 
         let canon_lockout = canon.lockout_time;
         let mut animation_timer = AnimationTimer(Timer::from_seconds(CANON_ANIMATION_SPEED, TimerMode::Repeating));
         animation_timer.0.pause();
         commands.spawn((
             SpriteSheetBundle {
-                texture: texture,
+                texture,
                 atlas: TextureAtlas {
                     layout: texture_atlas_layout,
                     index: animation_indices.first,
@@ -115,6 +115,7 @@ fn setup(
                 ..default()
             },
             canon,
+            Hitbox::aabb_for_sprite(Vec2::new(21., CANON_HEIGHT), 1.),
             animation_indices,
             ShootTimer(Timer::from_seconds(canon_lockout, TimerMode::Once)),
             animation_timer,
@@ -122,28 +123,32 @@ fn setup(
     }
 }
 
+// Canons are not children of the ship, so they outlive it
+fn despawn_canons(
+    mut commands: Commands,
+    canons: Query<Entity, With<Canon>>,
+) {
+    for entity in canons.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
 fn move_canon(
-    mut player_query: Query<&Transform, With<Player>>,
+    mut player_query: Query<(&Transform, &Hitbox), With<Player>>,
     time:Res<Time>,
-    mut canons: Query<(&mut Transform, &Canon), (With<Canon>, Without<Player>)>,
+    mut canons: Query<(&mut Transform, &Hitbox, &Canon), Without<Player>>,
 ) {
-    let player_transform = player_query.single_mut();
-    for (mut canon_transform, canon) in canons.iter_mut() {
+    let (player_transform, player_hitbox) = player_query.single_mut();
+    for (mut canon_transform, canon_hitbox, canon) in canons.iter_mut() {
         let transform_x_dim = match canon.position {
             Position::Left =>  -CANON_DISTANCE,
             Position::Right => CANON_DISTANCE,
         };
-        let canon_circle = BoundingCircle::new(
-            canon_transform.translation.truncate(),
-            CANON_HEIGHT
-        );
         let mut move_target = player_transform.translation;
         move_target.x += transform_x_dim;
-        let target_bb = Aabb2d::new(
-            move_target.truncate(),
-            player_transform.scale.truncate() / 2.
-        );
-        if !canon_circle.intersects(&target_bb) { 
+        // Close enough once it touches where the ship would be
+        let target_transform = player_transform.with_translation(move_target);
+        if !canon_hitbox.intersects(&canon_transform, player_hitbox, &target_transform) { 
             //move
             // let dir = Vec3::new(player_transform.translation.x - canon_transform.translation.x, player_transform.translation.y - canon_transform.translation.y, 0.0).normalize();
             let dir = move_target - canon_transform.translation;
@@ -153,14 +158,14 @@ fn move_canon(
 }
 
 fn fire_canon(
-    mut canons: Query<(&mut Transform, &mut Canon, &mut ShootTimer, &mut AnimationTimer), (With<Canon>, Without<Player>)>,
-    mouse_buttons:Res<ButtonInput<MouseButton>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut canons: Query<(&mut Transform, &mut Canon, &mut ShootTimer, &mut AnimationTimer), Without<Player>>,
+    input: Res<PlayerInput>,
     asset_server: Res<AssetServer>,
     time: Res<Time>,
+    mut pool: ResMut<ProjectilePool>,
     mut commands: Commands,
 ) {
-    if keyboard_input.pressed(KeyCode::Space) || mouse_buttons.pressed(MouseButton::Left) {
+    if input.alt_fire {
         for (canon_transform, mut canon, mut shoot_timer, mut animation_timer) in canons.iter_mut() {
             if shoot_timer.0.tick(time.delta()).finished() {
                 canon.needs_cooldown = false;
@@ -179,15 +184,10 @@ fn fire_canon(
                     canon.needs_cooldown = true;
                     shoot_timer.0.reset();
                     animation_timer.0.unpause();
-                    commands.spawn((
-                        SpriteBundle {
-                            transform: spawn_transform,
-                            texture: asset_server.load("plasma_orb.png"),
-                            ..default()
-                        },
+                    pool.acquire(&mut commands, &asset_server, &BeamType::PlasmaOrb, spawn_transform).insert(
                         //todo: 2 weapons, should be enum w/ params
                         plasma_orb,
-                    ));
+                    );
                 }
             }
         } 
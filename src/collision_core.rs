@@ -1,12 +1,18 @@
-use bevy::{app::{App, FixedUpdate, Plugin, Update}, prelude::*, time::Timer};
+use bevy::{app::{App, FixedUpdate, Plugin, Update}, math::bounding::{Aabb2d, BoundingVolume, IntersectsVolume}, prelude::*, time::Timer, utils::HashMap};
+
+use crate::{beam::{Beam, Faction}, enemy_core::EnemyCore, hitbox::Hitbox, projectile_pool::{Pooled, ProjectilePool}, GameState};
 
 pub struct CollisionCorePlugin;
 impl Plugin for CollisionCorePlugin {
     fn build(&self, app: &mut App) {
         app
         .add_event::<CollisionEvent>()
+        .init_resource::<CollisionGrid>()
+        .configure_sets(FixedUpdate, BroadphaseSet.run_if(in_state(GameState::Playing)))
+        .add_systems(FixedUpdate, (build_collision_grid).in_set(BroadphaseSet))
+        .add_systems(FixedUpdate, (cull_off_screen).before(BroadphaseSet).run_if(in_state(GameState::Playing)))
         .add_systems(Update, collision)
-        .add_systems(FixedUpdate, blink)
+        .add_systems(FixedUpdate, (blink).run_if(not(in_state(GameState::Paused))))
         ;
     }
 }
@@ -14,6 +20,133 @@ impl Plugin for CollisionCorePlugin {
 #[derive(Event)]
 pub struct CollisionEvent(pub Entity);
 
+/// Fills the `CollisionGrid`. Systems moving or spawning projectiles run before it,
+/// every collision check runs after it.
+#[derive(SystemSet, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct BroadphaseSet;
+
+// Roughly a few bullets across, big enough that most things sit in one cell
+const GRID_CELL_SIZE: f32 = 64.;
+
+/// Where every projectile was this fixed step, so collision checks only look
+/// at the ones nearby instead of all of them.
+#[derive(Resource, Default)]
+pub struct CollisionGrid {
+    player_fire: SpatialGrid,
+    enemy_fire: SpatialGrid,
+}
+impl CollisionGrid {
+    /// The beams fired by `faction`.
+    pub fn fired_by(&self, faction: Faction) -> &SpatialGrid {
+        match faction {
+            Faction::Player => &self.player_fire,
+            Faction::Enemy => &self.enemy_fire,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GridEntry {
+    pub entity: Entity,
+    // Around its hitbox
+    pub bounds: Aabb2d,
+}
+
+/// A uniform grid of buckets. Entries go in the cell their center is in, and
+/// queries reach out by the largest entry's size to still find ones poking over.
+#[derive(Default)]
+pub struct SpatialGrid {
+    cells: HashMap<IVec2, Vec<GridEntry>>,
+    reach: Vec2,
+}
+impl SpatialGrid {
+    fn cell(position: Vec2) -> IVec2 {
+        (position / GRID_CELL_SIZE).floor().as_ivec2()
+    }
+
+    /// Empties the grid, keeping the cells that were in use around for reuse.
+    pub fn clear(&mut self) {
+        self.cells.retain(|_, entries| {
+            let used = !entries.is_empty();
+            entries.clear();
+            used
+        });
+        self.reach = Vec2::ZERO;
+    }
+
+    pub fn insert(&mut self, entity: Entity, bounds: Aabb2d) {
+        self.reach = self.reach.max(bounds.half_size());
+        self.cells.entry(Self::cell(bounds.center())).or_default().push(GridEntry { entity, bounds });
+    }
+
+    /// Everything whose bounds overlap `area`. Still needs an exact test against the hitboxes.
+    pub fn query(&self, area: Aabb2d) -> impl Iterator<Item = &GridEntry> {
+        let min = Self::cell(area.min - self.reach);
+        let max = Self::cell(area.max + self.reach);
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter(move |entry| entry.bounds.intersects(&area))
+    }
+}
+
+fn build_collision_grid(
+    mut grid: ResMut<CollisionGrid>,
+    beam_query: Query<(Entity, &Transform, &Hitbox, &Beam)>,
+) {
+    let grid = grid.as_mut();
+    grid.player_fire.clear();
+    grid.enemy_fire.clear();
+    for (entity, transform, hitbox, beam) in beam_query.iter() {
+        let faction_grid = match beam.faction {
+            Faction::Player => &mut grid.player_fire,
+            Faction::Enemy => &mut grid.enemy_fire,
+        };
+        faction_grid.insert(entity, hitbox.bounds(transform));
+    }
+}
+
+// Half the window, the arena is centred on the origin
+pub const ARENA_HALF_SIZE: Vec2 = Vec2::new(600., 400.);
+// How far past the edge things get before they are culled, so they are fully out of sight
+const CULL_MARGIN: f32 = 64.;
+
+/// Put on enemies once they have been inside the arena. Most enemies spawn
+/// off screen and fly in, so only ones that have been in get culled for leaving.
+#[derive(Component)]
+pub struct EnteredArena;
+
+fn in_arena(position: Vec2, margin: f32) -> bool {
+    let reach = ARENA_HALF_SIZE + margin;
+    position.x.abs() <= reach.x && position.y.abs() <= reach.y
+}
+
+/// Releases projectiles and despawns enemies that have left the arena, so they
+/// stop costing anything. Runs before the broadphase so they are not checked either.
+fn cull_off_screen(
+    mut commands: Commands,
+    mut pool: ResMut<ProjectilePool>,
+    projectile_query: Query<(Entity, &Transform, Option<&Pooled>), With<Beam>>,
+    enemy_query: Query<(Entity, &Transform, Has<EnteredArena>), With<EnemyCore>>,
+) {
+    for (entity, transform, pooled) in projectile_query.iter() {
+        if !in_arena(transform.translation.truncate(), CULL_MARGIN) {
+            pool.release(&mut commands, entity, pooled);
+        }
+    }
+    for (entity, transform, entered) in enemy_query.iter() {
+        let position = transform.translation.truncate();
+        if entered {
+            if !in_arena(position, CULL_MARGIN) {
+                commands.entity(entity).despawn();
+            }
+        } else if in_arena(position, 0.) {
+            commands.entity(entity).try_insert(EnteredArena);
+        }
+    }
+}
+
 #[derive(Component, Resource)]
 pub struct Blinking(pub Timer);
 pub const BLINK_DURATION: f32 = 0.35;
@@ -24,11 +157,8 @@ pub fn collision (
 ) {
     if !collision_events.is_empty() {
         for event in collision_events.read() {
-            match commands.get_entity(event.0) {
-                Some(_) => {
-                    commands.entity(event.0).insert(Blinking(Timer::from_seconds(BLINK_DURATION, TimerMode::Once)));
-                },
-                None => {}
+            if let Some(mut entity) = commands.get_entity(event.0) {
+                entity.insert(Blinking(Timer::from_seconds(BLINK_DURATION, TimerMode::Once)));
             }
         }
     }
@@ -42,10 +172,87 @@ pub fn blink (
     for (mut blinking_timer, mut sprite, entity) in enemy_query.iter_mut() {
         
         let blinking_timer = blinking_timer.0.tick(time.delta());
-        sprite.color = Color::rgba(255., 255., 255., 1.);
+        // Keeps the alpha, which others use to fade things (e.g. phased out ghost orchids)
+        let alpha = sprite.color.a();
+        sprite.color = Color::rgba(255., 255., 255., alpha);
         if blinking_timer.finished() {
-            sprite.color = Color::rgba(1., 1., 1., 1.);
+            sprite.color = Color::rgba(1., 1., 1., alpha);
             commands.entity(entity).remove::<Blinking>();
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(grid: &SpatialGrid, area: Aabb2d) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = grid.query(area).map(|entry| entry.entity).collect();
+        entities.sort();
+        entities
+    }
+
+    #[test]
+    fn query_reaches_entry_straddling_cell_boundary() {
+        let mut grid = SpatialGrid::default();
+        let straddling = Entity::from_raw(1);
+        // Its center is in cell (0, 0), its right side pokes into (1, 0)
+        grid.insert(straddling, Aabb2d::new(Vec2::new(60., 10.), Vec2::splat(8.)));
+        assert_eq!(SpatialGrid::cell(Vec2::new(60., 10.)), IVec2::new(0, 0));
+
+        let cases = [
+            ("over the part in the next cell", Aabb2d::new(Vec2::new(66., 10.), Vec2::splat(1.)), vec![straddling]),
+            ("over the part in its own cell", Aabb2d::new(Vec2::new(54., 10.), Vec2::splat(1.)), vec![straddling]),
+            ("next cell, past its edge", Aabb2d::new(Vec2::new(70., 10.), Vec2::splat(1.)), vec![]),
+            ("own cell, clear of it", Aabb2d::new(Vec2::new(10., 10.), Vec2::splat(1.)), vec![]),
+        ];
+        for (name, area, expected) in cases {
+            assert_eq!(found(&grid, area), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn query_reaches_by_largest_entry() {
+        let mut grid = SpatialGrid::default();
+        let small = Entity::from_raw(1);
+        let large = Entity::from_raw(2);
+        grid.insert(small, Aabb2d::new(Vec2::new(-300., 0.), Vec2::splat(4.)));
+        // Reaches two cells out either way from the cell its center is in
+        grid.insert(large, Aabb2d::new(Vec2::ZERO, Vec2::splat(150.)));
+
+        assert_eq!(found(&grid, Aabb2d::new(Vec2::new(145., -145.), Vec2::splat(2.))), vec![large]);
+        assert_eq!(found(&grid, Aabb2d::new(Vec2::new(-150., 0.), Vec2::splat(150.))), vec![small, large]);
+        assert_eq!(found(&grid, Aabb2d::new(Vec2::new(160., 0.), Vec2::splat(2.))), vec![]);
+    }
+
+    #[test]
+    fn clear_forgets_entries_and_reach() {
+        let mut grid = SpatialGrid::default();
+        grid.insert(Entity::from_raw(1), Aabb2d::new(Vec2::ZERO, Vec2::splat(150.)));
+        grid.clear();
+        assert_eq!(grid.reach, Vec2::ZERO);
+        assert_eq!(found(&grid, Aabb2d::new(Vec2::ZERO, Vec2::splat(200.))), vec![]);
+
+        let small = Entity::from_raw(2);
+        grid.insert(small, Aabb2d::new(Vec2::new(10., 10.), Vec2::splat(4.)));
+        assert_eq!(found(&grid, Aabb2d::new(Vec2::new(12., 12.), Vec2::splat(1.))), vec![small]);
+    }
+
+    #[test]
+    fn blink_keeps_alpha() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        let faded = Sprite { color: Color::rgba(1., 1., 1., 0.25), ..default() };
+        let blinking = world.spawn((faded.clone(), Blinking(Timer::from_seconds(BLINK_DURATION, TimerMode::Once)))).id();
+        let finishing = world.spawn((faded, Blinking(Timer::from_seconds(0., TimerMode::Once)))).id();
+        world.run_system_once(blink);
+
+        for entity in [blinking, finishing] {
+            assert_eq!(world.get::<Sprite>(entity).unwrap().color.a(), 0.25);
+        }
+        assert!(world.get::<Blinking>(blinking).is_some());
+        assert!(world.get::<Blinking>(finishing).is_none());
+    }
+}
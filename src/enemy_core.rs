@@ -1,6 +1,7 @@
-use bevy::{prelude::*, utils::HashMap};
+use bevy::prelude::*;
+use serde::Deserialize;
 
-use crate::{basic_enemy, basic_enemy_move_patterns::EnemyMovePattern, beam::{Beam, ShootType}, level::Wave};
+use crate::{basic_enemy::HoldFire, basic_enemy_move_patterns::{EnemyMovePattern, StartShootGo}, beam::ShootType, boss::{Boss, BOSS_SCALE}, enemy_path::EnemyPath, ghost_orchid::GhostOrchid, hitbox::Hitbox, level::Wave, rose_gunner::RoseGunner, GameState};
 
 pub struct EnemyCorePlugin;
 impl Plugin for EnemyCorePlugin {
@@ -8,7 +9,9 @@ impl Plugin for EnemyCorePlugin {
         app
         .add_systems(Startup, init_assets)
         .add_event::<SpawnEnemyEvent>()
-        .add_systems(Update, spawn_enemy)
+        .add_event::<EnemyKilledEvent>()
+        .add_systems(FixedUpdate, spawn_enemy)
+        .add_systems(FixedUpdate, (advance_phase).run_if(in_state(GameState::Playing)))
         ;
     }
 }
@@ -23,13 +26,18 @@ pub struct EnemyCore {
     pub shoot: bool,
     pub shoot_type: ShootType,
     pub shoot_timer: ShootTimer,
+    // Upcoming phases, in the order they are reached
+    pub phases: Vec<EnemyPhase>,
+    // Only used by EnemyMovePattern::StartShootGo
+    pub start_shoot_go: StartShootGo,
+    // Only used by EnemyMovePattern::Path
+    pub path: EnemyPath,
 }
 impl EnemyCore {
     pub fn builder() -> EnemyCoreBuilder {
         EnemyCoreBuilder::default()
     }
 }
-#[derive(Default)]
 pub struct EnemyCoreBuilder {
     pub x_direction: f32,
     pub y_direction: f32,
@@ -39,9 +47,12 @@ pub struct EnemyCoreBuilder {
     shoot: bool,
     shoot_type: ShootType,
     shoot_timer: ShootTimer,
+    phases: Vec<EnemyPhase>,
+    start_shoot_go: StartShootGo,
+    path: EnemyPath,
 }
-impl EnemyCoreBuilder {
-    pub fn default() -> Self {
+impl Default for EnemyCoreBuilder {
+    fn default() -> Self {
         EnemyCoreBuilder {
             x_direction: 0.,
             y_direction: 0.,
@@ -51,9 +62,13 @@ impl EnemyCoreBuilder {
             shoot: true,
             shoot_type: ShootType::Basic,
             shoot_timer: ShootTimer(Timer::from_seconds(0.3, TimerMode::Repeating)),
+            phases: vec![],
+            start_shoot_go: StartShootGo::default(),
+            path: EnemyPath::default(),
         }
     }
-
+}
+impl EnemyCoreBuilder {
     pub fn direction(mut self, x: f32, y: f32) -> Self {
         self.x_direction = x;
         self.y_direction = y;
@@ -65,11 +80,6 @@ impl EnemyCoreBuilder {
         self
     }
 
-    pub fn state(mut self, state: EnemyState) -> Self {
-        self.state = state;
-        self
-    }
-
     pub fn shoot(mut self, shoot: bool) -> Self {
         self.shoot = shoot;
         self
@@ -90,6 +100,38 @@ impl EnemyCoreBuilder {
         self
     }
 
+    /// Where a `StartShootGo` enemy flies in to and stops.
+    pub fn stop_position(mut self, x: f32, y: f32) -> Self {
+        self.start_shoot_go.stop_position = Vec2::new(x, y);
+        self
+    }
+
+    /// How many volleys a `StartShootGo` enemy fires before leaving.
+    pub fn volleys(mut self, volleys: u32) -> Self {
+        self.start_shoot_go.volleys = volleys;
+        self
+    }
+
+    /// Which way a `StartShootGo` enemy leaves the screen.
+    pub fn exit_direction(mut self, x: f32, y: f32) -> Self {
+        self.start_shoot_go.exit_direction = Vec2::new(x, y);
+        self
+    }
+
+    /// Makes the enemy follow `path`, switching its move pattern to `EnemyMovePattern::Path`.
+    pub fn path(mut self, path: EnemyPath) -> Self {
+        self.path = path;
+        self.move_pattern = EnemyMovePattern::Path;
+        self
+    }
+
+    /// Adds a phase the enemy switches to once its health drops to `phase.health`.
+    pub fn phase(mut self, phase: EnemyPhase) -> Self {
+        self.phases.push(phase);
+        self.phases.sort_by(|a, b| b.health.total_cmp(&a.health));
+        self
+    }
+
     pub fn build(self) -> EnemyCore {
         EnemyCore {
             x_direction: self.x_direction,
@@ -100,6 +142,9 @@ impl EnemyCoreBuilder {
             shoot: self.shoot,
             shoot_type: self.shoot_type,
             shoot_timer: self.shoot_timer,
+            phases: self.phases,
+            start_shoot_go: self.start_shoot_go,
+            path: self.path,
         }
     }
 }
@@ -119,19 +164,60 @@ impl Default for EnemyCoreBundle {
             state: EnemyState::Active,
             shoot: false,
             shoot_type: ShootType::Basic,
-            shoot_timer: ShootTimer(Timer::from_seconds(0.3, TimerMode::Repeating))
+            shoot_timer: ShootTimer(Timer::from_seconds(0.3, TimerMode::Repeating)),
+            phases: vec![],
+            start_shoot_go: StartShootGo::default(),
+            path: EnemyPath::default(),
             }
         }
     }
 }
 
-#[derive(Default)]
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
 pub enum EnemyType {
     #[default]
     Basic,
     EnemyB,
     GhostOrchid,
     RoseGunner,
+    Boss,
+}
+impl EnemyType {
+    /// Sized from the type's sprite, see get_enemy_texture.
+    pub fn hitbox(&self) -> Hitbox {
+        match self {
+            EnemyType::Basic => Hitbox::circle_for_sprite(Vec2::new(21., 27.), 1.),
+            EnemyType::EnemyB | EnemyType::Boss => Hitbox::circle_for_sprite(Vec2::new(45., 45.), 0.9),
+            EnemyType::GhostOrchid => Hitbox::capsule_for_sprite(Vec2::new(50., 83.), 0.8),
+            EnemyType::RoseGunner => Hitbox::circle_for_sprite(Vec2::new(37., 35.), 0.9),
+        }
+    }
+
+    /// Points awarded for destroying an enemy of this type.
+    pub fn score(&self) -> i32 {
+        match self {
+            EnemyType::Basic => 100,
+            EnemyType::EnemyB => 250,
+            EnemyType::GhostOrchid => 300,
+            EnemyType::RoseGunner => 400,
+            EnemyType::Boss => 5000,
+        }
+    }
+}
+
+/// Sent when the player destroys an enemy. Enemies leaving the screen do not count.
+#[derive(Event, Debug)]
+pub struct EnemyKilledEvent {
+    pub enemy_type: EnemyType,
+}
+
+/// Swaps how an enemy moves and shoots once its health drops far enough.
+#[derive(Clone, Debug)]
+pub struct EnemyPhase {
+    pub health: f32,
+    pub move_pattern: EnemyMovePattern,
+    pub shoot_type: ShootType,
+    pub shoot_timer: ShootTimer,
 }
 
 #[derive(Event)]
@@ -179,7 +265,7 @@ fn init_assets(
     );
 }
 
-fn spawn_enemy(
+pub fn spawn_enemy(
     mut commands: Commands,
     mut events: EventReader<SpawnEnemyEvent>,
     enemy_handles: ResMut<EnemyHandles>,
@@ -187,20 +273,40 @@ fn spawn_enemy(
     if !events.is_empty() {
         for event in events.read() {
             let (enemy_core_bundle, enemy_type, transform, wave) = &event.0;
-            let texture = get_enemy_texture(&enemy_type, enemy_handles.as_ref());
+            let texture = get_enemy_texture(enemy_type, enemy_handles.as_ref());
             let enemy_core = enemy_core_bundle.enemy_core.clone();
+            let max_health = enemy_core.health;
 
-            commands.spawn((
+            let mut enemy = commands.spawn((
                 EnemyCoreBundle {
                     enemy_core,
                 },
                 SpriteBundle {
                     texture,
-                    transform: transform.clone(),
+                    transform: *transform,
                     ..default()
                     },
+                *enemy_type,
+                enemy_type.hitbox(),
                 wave.clone()
             ));
+            // Per-type behavior hangs off extra components
+            match enemy_type {
+                EnemyType::Boss => {
+                    enemy.insert((
+                        Boss { max_health },
+                        transform.with_scale(Vec3::splat(BOSS_SCALE)),
+                    ));
+                }
+                EnemyType::GhostOrchid => {
+                    enemy.insert(GhostOrchid::default());
+                }
+                EnemyType::RoseGunner => {
+                    // Starts out tracking the player, not firing
+                    enemy.insert((RoseGunner::default(), HoldFire));
+                }
+                _ => {}
+            }
         }
     }
 }
@@ -214,5 +320,20 @@ fn get_enemy_texture(
         EnemyType::EnemyB => enemy_handles.enemy_b.clone(),
         EnemyType::GhostOrchid => enemy_handles.ghost_orchid.clone(),
         EnemyType::RoseGunner => enemy_handles.rose_gunner.clone(),
+        EnemyType::Boss => enemy_handles.enemy_b.clone(),
+    }
+}
+
+fn advance_phase(
+    mut query: Query<&mut EnemyCore>,
+) {
+    for mut enemy in query.iter_mut() {
+        let next_phase_reached = enemy.phases.first().is_some_and(|phase| enemy.health <= phase.health);
+        if next_phase_reached {
+            let phase = enemy.phases.remove(0);
+            enemy.move_pattern = phase.move_pattern;
+            enemy.shoot_type = phase.shoot_type;
+            enemy.shoot_timer = phase.shoot_timer;
+        }
     }
 }
\ No newline at end of file
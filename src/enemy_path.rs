@@ -0,0 +1,306 @@
+use bevy::{math::cubic_splines::{CubicBezier, CubicCardinalSpline, CubicCurve, CubicGenerator}, prelude::*};
+use serde::Deserialize;
+
+// Points sampled along a segment to measure its length and keep the speed even along it
+const LENGTH_SAMPLES: usize = 32;
+
+/// An authored route for `EnemyMovePattern::Path`. Each segment starts where the
+/// previous one ended, the first one from wherever the enemy is when it starts
+/// following the path. Points are in world coordinates.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct EnemyPath {
+    pub segments: Vec<PathSegment>,
+    pub end: PathEnd,
+    #[serde(skip)]
+    progress: PathProgress,
+}
+impl EnemyPath {
+    /// Moves `delta_seconds` further along the path from `position`.
+    /// Returns where the enemy should be now, or `None` once a `PathEnd::Despawn` path is done.
+    pub fn advance(&mut self, position: Vec2, delta_seconds: f32) -> Option<Vec2> {
+        let mut remaining = delta_seconds;
+        let mut position = position;
+        // A segment too short to take any time still has to be stepped over,
+        // bounded so a path of nothing but those can not spin forever
+        for _ in 0..=self.segments.len() {
+            if self.progress.segment >= self.segments.len() {
+                match self.end {
+                    PathEnd::Stop => return Some(position),
+                    PathEnd::Despawn => return None,
+                    PathEnd::Loop if self.segments.is_empty() => return Some(position),
+                    PathEnd::Loop => self.progress = PathProgress::default(),
+                }
+            }
+            let segment = &self.segments[self.progress.segment];
+            let curve = self.progress.curve.get_or_insert_with(|| segment.curve(position));
+            let elapsed = self.progress.elapsed + remaining;
+            if elapsed < curve.duration {
+                let next = curve.position(segment.easing.apply(elapsed / curve.duration));
+                self.progress.elapsed = elapsed;
+                return Some(next);
+            }
+            // Finished this segment, whatever time is left goes to the next one
+            remaining = elapsed - curve.duration;
+            position = curve.position(1.);
+            self.progress = PathProgress {
+                segment: self.progress.segment + 1,
+                ..default()
+            };
+            if remaining <= 0. {
+                return Some(position);
+            }
+        }
+        Some(position)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct PathProgress {
+    segment: usize,
+    elapsed: f32,
+    // Built when the segment starts
+    curve: Option<SegmentCurve>,
+}
+
+// A segment's curve, measured so it can be followed at an even speed
+#[derive(Clone, Debug)]
+struct SegmentCurve {
+    curve: CubicCurve<Vec2>,
+    // How far along the curve each of its LENGTH_SAMPLES + 1 evenly spaced parameters is
+    distances: Vec<f32>,
+    // Seconds it takes
+    duration: f32,
+}
+impl SegmentCurve {
+    /// The point `fraction` of the way along the curve by distance travelled.
+    fn position(&self, fraction: f32) -> Vec2 {
+        let length = self.distances[LENGTH_SAMPLES];
+        let distance = fraction.clamp(0., 1.) * length;
+        let sample = self.distances.partition_point(|d| *d < distance).clamp(1, LENGTH_SAMPLES);
+        let (from, to) = (self.distances[sample - 1], self.distances[sample]);
+        let within = if to > from { (distance - from) / (to - from) } else { 0. };
+        let spans = self.curve.segments().len() as f32;
+        self.curve.position(spans * (sample as f32 - 1. + within) / LENGTH_SAMPLES as f32)
+    }
+}
+
+/// What happens once the last segment is done.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum PathEnd {
+    // Meant for paths that leave the screen
+    #[default]
+    Despawn,
+    Stop,
+    Loop,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PathSegment {
+    pub shape: SegmentShape,
+    // In pixels per second, kept the same all along the segment unless eased
+    pub speed: f32,
+    #[serde(default)]
+    pub easing: Easing,
+}
+impl PathSegment {
+    fn curve(&self, start: Vec2) -> SegmentCurve {
+        let curve = match &self.shape {
+            SegmentShape::Line { to } => {
+                CubicBezier::new([[start, start.lerp(*to, 1. / 3.), start.lerp(*to, 2. / 3.), *to]]).to_curve()
+            }
+            SegmentShape::Bezier { control1, control2, to } => {
+                CubicBezier::new([[start, *control1, *control2, *to]]).to_curve()
+            }
+            SegmentShape::CatmullRom { points } if !points.is_empty() => {
+                // Repeating the ends makes the spline pass through every point
+                let last = points[points.len() - 1];
+                let control_points: Vec<Vec2> = [start, start].into_iter()
+                    .chain(points.iter().copied())
+                    .chain([last])
+                    .collect();
+                CubicCardinalSpline::new_catmull_rom(control_points).to_curve()
+            }
+            SegmentShape::CatmullRom { .. } => CubicBezier::new([[start; 4]]).to_curve(),
+        };
+        let spans = curve.segments().len() as f32;
+        let mut distances = Vec::with_capacity(LENGTH_SAMPLES + 1);
+        let mut length = 0.;
+        let mut previous = curve.position(0.);
+        distances.push(0.);
+        for i in 1..=LENGTH_SAMPLES {
+            let next = curve.position(spans * i as f32 / LENGTH_SAMPLES as f32);
+            length += previous.distance(next);
+            distances.push(length);
+            previous = next;
+        }
+        let duration = if self.speed > 0. { length / self.speed } else { 0. };
+        SegmentCurve { curve, distances, duration }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum SegmentShape {
+    Line { to: Vec2 },
+    // Cubic, starting from the end of the previous segment
+    Bezier { control1: Vec2, control2: Vec2, to: Vec2 },
+    // Passes through every point
+    CatmullRom { points: Vec<Vec2> },
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+impl Easing {
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2. - t),
+            Easing::EaseInOut => t * t * (3. - 2. * t),
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: f32 = 1. / 60.;
+    const SPEED: f32 = 200.;
+
+    fn segment(shape: SegmentShape) -> PathSegment {
+        PathSegment { shape, speed: SPEED, easing: Easing::Linear }
+    }
+
+    // The same as a wave file path with no progress yet
+    fn path(segments: Vec<PathSegment>, end: PathEnd) -> EnemyPath {
+        EnemyPath { segments, end, ..default() }
+    }
+
+    fn shapes() -> Vec<(&'static str, SegmentShape, Vec2)> {
+        vec![
+            ("line", SegmentShape::Line { to: Vec2::new(300., 0.) }, Vec2::new(300., 0.)),
+            (
+                "arch",
+                SegmentShape::Bezier { control1: Vec2::new(0., 200.), control2: Vec2::new(300., 200.), to: Vec2::new(300., 0.) },
+                Vec2::new(300., 0.),
+            ),
+            // Control points bunched at one end, which a plain Bezier parameter would rush through
+            (
+                "lopsided bezier",
+                SegmentShape::Bezier { control1: Vec2::new(20., 300.), control2: Vec2::new(300., -100.), to: Vec2::new(300., 0.) },
+                Vec2::new(300., 0.),
+            ),
+            (
+                "catmull-rom",
+                SegmentShape::CatmullRom { points: vec![Vec2::new(100., 100.), Vec2::new(200., 0.), Vec2::new(350., 50.)] },
+                Vec2::new(350., 50.),
+            ),
+        ]
+    }
+
+    // Where the enemy is after each step until the path is done, starting from the origin
+    fn follow(path: &mut EnemyPath, max_steps: usize) -> Vec<Vec2> {
+        let mut positions = vec![Vec2::ZERO];
+        for _ in 0..max_steps {
+            match path.advance(*positions.last().unwrap(), STEP) {
+                Some(next) => positions.push(next),
+                None => break,
+            }
+        }
+        positions
+    }
+
+    #[test]
+    fn segments_end_on_their_end_point() {
+        for (name, shape, end) in shapes() {
+            let mut path = path(vec![segment(shape)], PathEnd::Stop);
+            let positions = follow(&mut path, 600);
+            assert!(positions.last().unwrap().distance(end) < 1e-3, "{} ended at {:?}", name, positions.last());
+            // Stops there rather than drifting on
+            assert_eq!(path.advance(end, STEP), Some(end), "{}", name);
+        }
+    }
+
+    #[test]
+    fn speed_stays_even_along_segments() {
+        for (name, shape, _) in shapes() {
+            let mut path = path(vec![segment(shape)], PathEnd::Despawn);
+            let positions = follow(&mut path, 600);
+            let speeds: Vec<f32> = positions.windows(2).map(|step| step[0].distance(step[1]) / STEP).collect();
+            // The last step only covers what was left of the segment
+            for (i, speed) in speeds[..speeds.len() - 1].iter().enumerate() {
+                assert!((speed - SPEED).abs() < SPEED * 0.15, "{} moved at {} on step {}", name, speed, i);
+            }
+        }
+    }
+
+    #[test]
+    fn segments_follow_on_from_each_other() {
+        let ends = [Vec2::new(0., -100.), Vec2::new(200., -100.), Vec2::new(200., 100.)];
+        let mut path = path(
+            vec![
+                segment(SegmentShape::Line { to: ends[0] }),
+                segment(SegmentShape::Bezier { control1: Vec2::new(50., -200.), control2: Vec2::new(150., -200.), to: ends[1] }),
+                segment(SegmentShape::CatmullRom { points: vec![Vec2::new(250., 0.), ends[2]] }),
+            ],
+            PathEnd::Despawn,
+        );
+        let positions = follow(&mut path, 1200);
+        // Despawned before running out of steps
+        assert!(positions.len() < 1200);
+        for end in ends {
+            let closest = positions.iter().map(|position| position.distance(end)).fold(f32::MAX, f32::min);
+            assert!(closest <= SPEED * STEP, "never got near {:?}", end);
+        }
+        assert_eq!(path.advance(ends[2], STEP), None);
+    }
+
+    #[test]
+    fn looping_path_comes_back_round() {
+        let mut path = path(
+            vec![
+                segment(SegmentShape::Line { to: Vec2::new(100., 0.) }),
+                segment(SegmentShape::Line { to: Vec2::ZERO }),
+            ],
+            PathEnd::Loop,
+        );
+        // One second per lap
+        let positions = follow(&mut path, 150);
+        assert!(positions[60].length() < 1e-2, "{:?}", positions[60]);
+        assert!(positions[90].distance(Vec2::new(100., 0.)) < 1e-2, "{:?}", positions[90]);
+    }
+
+    #[test]
+    fn easing_keeps_its_end_points() {
+        for easing in [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+            assert_eq!(easing.apply(0.), 0., "{:?}", easing);
+            assert_eq!(easing.apply(1.), 1., "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn wave_file_paths_load_and_finish() {
+        let mut path: EnemyPath = ron::from_str(
+            "(
+                segments: [
+                    (shape: Line(to: (0., -100.)), speed: 200.),
+                    (shape: Bezier(control1: (50., -200.), control2: (150., -200.), to: (200., -100.)), speed: 200., easing: EaseIn),
+                ],
+            )",
+        ).unwrap();
+        assert_eq!(path.end, PathEnd::Despawn);
+        let positions = follow(&mut path, 1200);
+        assert!(positions.len() < 1200);
+        assert!(positions.last().unwrap().distance(Vec2::new(200., -100.)) < 1.);
+    }
+}
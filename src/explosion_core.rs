@@ -1,4 +1,6 @@
-use bevy::{app::{App, FixedUpdate, Plugin, Update}, asset::{AssetServer, Assets}, math::Vec2, prelude::{default, Bundle, Commands, Component, Deref, DerefMut, Entity, Event, EventReader, IntoSystemConfigs, Query, Res, ResMut, With}, render::color::Color, sprite::{Sprite, SpriteSheetBundle, TextureAtlas, TextureAtlasLayout}, time::{Time, Timer, TimerMode}, transform::components::Transform};
+use bevy::{app::{App, FixedUpdate, Plugin, Update}, asset::{AssetServer, Assets}, math::Vec2, prelude::{default, Bundle, Commands, Component, Deref, DerefMut, Entity, Event, EventReader, in_state, not, IntoSystemConfigs, Query, Res, ResMut, With}, render::color::Color, sprite::{Sprite, SpriteSheetBundle, TextureAtlas, TextureAtlasLayout}, time::{Time, Timer, TimerMode}, transform::components::Transform};
+
+use crate::GameState;
 
 pub struct ExplosionCorePlugin;
 impl Plugin for ExplosionCorePlugin {
@@ -6,7 +8,7 @@ impl Plugin for ExplosionCorePlugin {
         app
         .add_event::<ExplosionEvent>()
         .add_systems(Update, spawn_explosion)
-        .add_systems(FixedUpdate, (animate_explosion).chain())
+        .add_systems(FixedUpdate, (animate_explosion).run_if(not(in_state(GameState::Paused))))
         ;
     }
 }
@@ -38,7 +40,7 @@ impl Default for ExplosionBundle {
     fn default() -> Self {
         let animation_indices = AnimationIndices { first: 0, last: 4 };
         Self {
-            animation_indices: animation_indices,
+            animation_indices,
             animation_timer: AnimationTimer(Timer::from_seconds(0.12, TimerMode::Repeating)),
             explosion: Explosion,
         }
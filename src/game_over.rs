@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+
+use crate::{level::Wave, main_menu::StartRunEvent, player_input::PlayerInput, Game, GameLevel, GameState};
+
+pub struct GameOverPlugin;
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(OnEnter(GameState::GameOver), spawn_summary)
+        .add_systems(OnExit(GameState::GameOver), despawn_summary)
+        .add_systems(FixedUpdate, (restart).run_if(in_state(GameState::GameOver)))
+        ;
+    }
+}
+
+const SUMMARY_TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+
+#[derive(Component)]
+struct GameOverSummary;
+
+/// Shows how far the run got, on top of whatever is left of it.
+fn spawn_summary(
+    mut commands: Commands,
+    game: Res<Game>,
+    wave: Res<State<Wave>>,
+    level: Res<State<GameLevel>>,
+) {
+    let line = |text: String, font_size: f32| TextBundle::from_section(
+        text,
+        TextStyle { font_size, color: SUMMARY_TEXT_COLOR, ..default() },
+    );
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(12.),
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.6).into(),
+            ..default()
+        },
+        GameOverSummary,
+    )).with_children(|parent| {
+        // Beating the last level ends the run on Wave::None
+        let title = if *wave.get() == Wave::None { "RUN COMPLETE" } else { "GAME OVER" };
+        parent.spawn(line(title.to_string(), 64.));
+        parent.spawn(line(format!("Score: {}", game.score), 32.));
+        parent.spawn(line(format!("Reached {:?}, wave {:?}", level.get(), wave.get()), 24.));
+        parent.spawn(line("Press Enter or (A) to play again".to_string(), 20.));
+    });
+}
+
+fn despawn_summary(
+    mut commands: Commands,
+    summary_query: Query<Entity, With<GameOverSummary>>,
+) {
+    for entity in summary_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Starts a fresh run from the first wave of the level the run started on. Each plugin clears out its own
+/// leftovers when `GameState::GameOver` is exited.
+fn restart(
+    input: Res<PlayerInput>,
+    mut start_run_events: EventWriter<StartRunEvent>,
+) {
+    if input.confirm {
+        start_run_events.send(StartRunEvent(None));
+    }
+}
@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+use crate::{basic_enemy::{EnemyBehaviorSet, Intangible}, replay::GameRng};
+
+pub struct GhostOrchidPlugin;
+impl Plugin for GhostOrchidPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(FixedUpdate, (phase_ghost_orchid).in_set(EnemyBehaviorSet))
+        ;
+    }
+}
+
+// Seconds spent solid, firing
+const SOLID_TIME: f32 = 2.5;
+// Seconds spent phased out, drifting to a new spot
+const PHASED_TIME: f32 = 1.2;
+const PHASED_ALPHA: f32 = 0.25;
+// Where a ghost orchid can reappear
+const REPOSITION_MIN: Vec2 = Vec2::new(-500., 150.);
+const REPOSITION_MAX: Vec2 = Vec2::new(500., 400.);
+
+/// A GhostOrchid keeps phasing out, drifting somewhere else while it can not be
+/// hit, and phasing back in to fire.
+#[derive(Component, Debug)]
+pub struct GhostOrchid {
+    timer: Timer,
+    phased: bool,
+    from: Vec2,
+    to: Vec2,
+}
+impl Default for GhostOrchid {
+    fn default() -> Self {
+        GhostOrchid {
+            timer: Timer::from_seconds(SOLID_TIME, TimerMode::Once),
+            phased: false,
+            from: Vec2::ZERO,
+            to: Vec2::ZERO,
+        }
+    }
+}
+
+fn phase_ghost_orchid(
+    mut commands: Commands,
+    mut query: Query<(&mut GhostOrchid, &mut Transform, &mut Sprite, Entity)>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    for (mut ghost, mut transform, mut sprite, entity) in query.iter_mut() {
+        ghost.timer.tick(time.delta());
+        if ghost.phased {
+            // Drifts over while phased out, overriding its move pattern
+            let t = ghost.timer.fraction();
+            let position = ghost.from.lerp(ghost.to, t * t * (3. - 2. * t));
+            transform.translation = position.extend(transform.translation.z);
+        }
+        if !ghost.timer.finished() {
+            continue;
+        }
+        if ghost.phased {
+            ghost.phased = false;
+            ghost.timer = Timer::from_seconds(SOLID_TIME, TimerMode::Once);
+            sprite.color.set_a(1.);
+            commands.entity(entity).remove::<Intangible>();
+        } else {
+            ghost.phased = true;
+            ghost.timer = Timer::from_seconds(PHASED_TIME, TimerMode::Once);
+            ghost.from = transform.translation.truncate();
+            ghost.to = REPOSITION_MIN + Vec2::new(rng.0.f32(), rng.0.f32()) * (REPOSITION_MAX - REPOSITION_MIN);
+            sprite.color.set_a(PHASED_ALPHA);
+            commands.entity(entity).insert(Intangible);
+        }
+    }
+}
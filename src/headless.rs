@@ -0,0 +1,341 @@
+use std::time::Duration;
+
+use bevy::{
+    app::AppExit,
+    asset::{io::Reader, AssetLoader, LoadContext},
+    input::InputPlugin,
+    log::LogPlugin,
+    prelude::*,
+    time::TimeUpdateStrategy,
+    utils::BoxedFuture,
+};
+
+use crate::{
+    basic_enemy,
+    beam,
+    boss,
+    camera,
+    collision_core,
+    enemy_core::{self, EnemyCore},
+    explosion_core,
+    game_over,
+    ghost_orchid,
+    hud,
+    level::{self, Wave},
+    level_complete,
+    player::{self, Player, PlayerState},
+    keymap,
+    main_menu,
+    menu,
+    pause_menu,
+    player_input,
+    projectile_pool,
+    replay::{self, ReplayMode},
+    rose_gunner,
+    score,
+    Difficulty,
+    Game,
+    GameLevel,
+    GameState
+};
+
+// Every update advances time by exactly one frame, so runs are repeatable
+pub const HEADLESS_FRAME_TIME: f32 = 1. / 60.;
+
+/// Builds the game on top of `MinimalPlugins`, without a window or renderer.
+/// Sprites are still spawned, they are just never drawn.
+/// Call `app.finish()` and `app.cleanup()` before stepping it with `app.update()`.
+pub fn headless_app() -> App {
+    let mut app = App::new();
+    app
+        .add_plugins((
+            MinimalPlugins,
+            LogPlugin::default(),
+            AssetPlugin::default(),
+            InputPlugin,
+        ))
+        // These are registered by the window and render plugins in the windowed game
+        .add_event::<CursorMoved>()
+        .init_asset::<Image>()
+        .register_asset_loader(HeadlessImageLoader)
+        .init_asset::<TextureAtlasLayout>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(HEADLESS_FRAME_TIME)))
+        .add_plugins((
+            camera::GameCameraPlugin,
+            collision_core::CollisionCorePlugin,
+            enemy_core::EnemyCorePlugin,
+            explosion_core::ExplosionCorePlugin,
+            game_over::GameOverPlugin,
+            hud::HudPlugin,
+            level::LevelPlugin,
+            basic_enemy::BasicEnemyPlugin,
+            player::PlayerPlugin,
+            beam::BeamPlugin,
+            boss::BossPlugin,
+            keymap::KeymapPlugin,
+            player_input::PlayerInputPlugin,
+            replay::ReplayPlugin,
+            score::ScorePlugin,
+        ))
+        .add_plugins((
+            ghost_orchid::GhostOrchidPlugin,
+            projectile_pool::ProjectilePoolPlugin,
+            rose_gunner::RoseGunnerPlugin,
+            menu::MenuPlugin,
+            main_menu::MainMenuPlugin,
+            pause_menu::PauseMenuPlugin,
+            level_complete::LevelCompletePlugin,
+        ))
+        .add_systems(OnEnter(GameState::MainMenu), main_menu::skip_main_menu)
+        .init_resource::<Game>()
+        .init_resource::<Difficulty>()
+        .init_state::<GameState>()
+        .init_state::<GameLevel>();
+    app
+}
+
+/// Finishes setting up a headless app and steps it through loading and the
+/// skipped main menu, until the ship is flying.
+pub fn start_playing(app: &mut App) {
+    app.insert_resource(ReplayMode::Off);
+    app.finish();
+    app.cleanup();
+    for _ in 0..600 {
+        app.update();
+        let playing = *app.world.resource::<State<GameState>>().get() == GameState::Playing;
+        let spawned = *app.world.resource::<State<PlayerState>>().get() == PlayerState::Spawned;
+        if playing && spawned {
+            break;
+        }
+    }
+}
+
+/// Stands in for the render plugin's image loader, so sprites load as blank
+/// images instead of each one logging that no loader could be found.
+struct HeadlessImageLoader;
+impl AssetLoader for HeadlessImageLoader {
+    type Asset = Image;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    fn load<'a>(
+        &'a self,
+        _reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Image, Self::Error>> {
+        Box::pin(async { Ok(Image::default()) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["png"]
+    }
+}
+
+/// Steps the headless game for a number of frames and logs where the run ended up.
+pub fn run(frames: u32, replay_mode: ReplayMode, difficulty: Difficulty) {
+    let mut app = headless_app();
+    app.insert_resource(replay_mode);
+    app.insert_resource(difficulty);
+    app.finish();
+    app.cleanup();
+    for _ in 0..frames {
+        app.update();
+    }
+    // Lets anything listening for the exit (e.g. replay recording) finish up
+    app.world.send_event(AppExit);
+    app.update();
+
+    let world = &mut app.world;
+    let game_state = world.resource::<State<GameState>>().get().clone();
+    let wave = world.resource::<State<Wave>>().get().clone();
+    let score = world.resource::<Game>().score;
+    let enemies = world.query::<&EnemyCore>().iter(world).count();
+    let player = world.query::<&Player>().get_single(world).ok().map(|p| format!("{:?}", p));
+    info!("headless run finished after {} frames: {:?}, wave {:?}, score {}, {} enemies alive, player {:?}", frames, game_state, wave, score, enemies, player);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Long enough to leave the menus, spawn the first wave and take some fire
+    const SMOKE_FRAMES: u32 = 1200;
+
+    #[test]
+    fn headless_run_reaches_play() {
+        let mut app = headless_app();
+        app.finish();
+        app.cleanup();
+        let mut saw_enemies = false;
+        for _ in 0..SMOKE_FRAMES {
+            app.update();
+            let world = &mut app.world;
+            saw_enemies |= world.query::<&EnemyCore>().iter(world).next().is_some();
+        }
+
+        let world = &mut app.world;
+        let game_state = world.resource::<State<GameState>>().get().clone();
+        assert!(matches!(game_state, GameState::Playing | GameState::GameOver), "ended up in {:?}", game_state);
+        assert!(saw_enemies, "no enemies spawned in {} frames", SMOKE_FRAMES);
+        assert_eq!(world.query::<&Player>().iter(world).count(), 1);
+    }
+
+    // Enough for both levels with every enemy shot the moment it can be
+    const CLEAR_FRAMES: u32 = 60 * 300;
+
+    #[test]
+    fn every_wave_can_be_cleared() {
+        let mut app = headless_app();
+        app.finish();
+        app.cleanup();
+        let mut completed = vec![];
+        for _ in 0..CLEAR_FRAMES {
+            app.update();
+            let world = &mut app.world;
+            let game_state = world.resource::<State<GameState>>().get().clone();
+            if game_state == GameState::LevelComplete {
+                let level = *world.resource::<State<GameLevel>>().get();
+                if !completed.contains(&level) {
+                    completed.push(level);
+                }
+            }
+            if completed.len() == GameLevel::ALL.len() {
+                break;
+            }
+            assert_ne!(game_state, GameState::GameOver, "the run ended having completed {:?}", completed);
+
+            // A player who never gets hurt, and hits every enemy as soon as it is in reach
+            let players: Vec<Entity> = world.query_filtered::<Entity, With<Player>>().iter(world).collect();
+            for player in players {
+                world.entity_mut(player).insert(player::Invulnerable(Timer::from_seconds(1., TimerMode::Once)));
+            }
+            let targets: Vec<Transform> = world.query_filtered::<&Transform, (With<EnemyCore>, With<collision_core::EnteredArena>)>()
+                .iter(world)
+                .copied()
+                .collect();
+            for target in targets {
+                let mut beam = beam::Beam::new(&beam::BeamType::Laser, Vec2::Y);
+                beam.power = 1e6;
+                world.spawn((beam, beam::BeamType::Laser.hitbox(), target));
+            }
+        }
+        let world = &mut app.world;
+        let wave = world.resource::<State<Wave>>().get().clone();
+        let level = *world.resource::<State<GameLevel>>().get();
+        assert_eq!(completed, GameLevel::ALL, "stuck on {:?} wave {:?}", level, wave);
+    }
+
+    // Fixed steps into a run when a replay is compared
+    const REPLAY_STEPS: u32 = 1800;
+
+    // Where a run stands after REPLAY_STEPS fixed steps of play
+    #[derive(Debug, PartialEq)]
+    struct RunSnapshot {
+        game_state: GameState,
+        wave: Wave,
+        score: i32,
+        player: Option<(Vec3, f32, f32)>,
+        enemies: Vec<Vec3>,
+    }
+
+    #[derive(Resource, Default)]
+    struct ReplaySteps {
+        steps: u32,
+        snapshot: Option<RunSnapshot>,
+    }
+
+    fn snapshot_run(world: &mut World) {
+        if matches!(world.resource::<State<GameState>>().get(), GameState::Loading | GameState::MainMenu) {
+            return;
+        }
+        let mut replay_steps = world.remove_resource::<ReplaySteps>().unwrap();
+        replay_steps.steps += 1;
+        if replay_steps.steps == REPLAY_STEPS {
+            let player = world.query::<(&Transform, &Player)>()
+                .get_single(world)
+                .ok()
+                .map(|(transform, player)| (transform.translation, player.health, player.shield));
+            let mut enemies: Vec<Vec3> = world.query_filtered::<&Transform, With<EnemyCore>>()
+                .iter(world)
+                .map(|transform| transform.translation)
+                .collect();
+            enemies.sort_by(|a, b| a.to_array().partial_cmp(&b.to_array()).unwrap());
+            replay_steps.snapshot = Some(RunSnapshot {
+                game_state: world.resource::<State<GameState>>().get().clone(),
+                wave: world.resource::<State<Wave>>().get().clone(),
+                score: world.resource::<Game>().score,
+                player,
+                enemies,
+            });
+        }
+        world.insert_resource(replay_steps);
+    }
+
+    fn play_back(replay_path: &std::path::Path, steps_per_frame: u32) -> RunSnapshot {
+        let mut app = headless_app();
+        let frame_time = Time::<Fixed>::default().timestep() * steps_per_frame;
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(frame_time))
+            .insert_resource(ReplayMode::Playback(replay_path.to_path_buf()))
+            .init_resource::<ReplaySteps>()
+            .add_systems(FixedPostUpdate, snapshot_run);
+        app.finish();
+        app.cleanup();
+        for _ in 0..(REPLAY_STEPS * 2) {
+            app.update();
+            if let Some(snapshot) = app.world.resource_mut::<ReplaySteps>().snapshot.take() {
+                return snapshot;
+            }
+        }
+        panic!("the run never got {} steps in", REPLAY_STEPS);
+    }
+
+    #[test]
+    fn replays_do_not_depend_on_frame_rate() {
+        // Weaves side to side, firing all the way
+        let frames = (0..REPLAY_STEPS)
+            .map(|step| player_input::PlayerInput {
+                movement: if (step / 90) % 2 == 0 { Vec2::NEG_X } else { Vec2::X },
+                fire: true,
+                alt_fire: step % 200 < 100,
+                ..default()
+            })
+            .collect();
+        let replay = replay::Replay { seed: 7, frames, ..default() };
+        let replay_path = std::env::temp_dir().join(format!("frame_rate_{}.replay.ron", std::process::id()));
+        std::fs::write(&replay_path, ron::to_string(&replay).unwrap()).unwrap();
+
+        let one_step = play_back(&replay_path, 1);
+        let four_steps = play_back(&replay_path, 4);
+        let _ = std::fs::remove_file(&replay_path);
+        assert_eq!(one_step, four_steps);
+    }
+
+    #[test]
+    fn replays_start_on_the_recorded_level() {
+        let replay_path = std::env::temp_dir().join(format!("start_level_{}.replay.ron", std::process::id()));
+
+        // Picked from Level Select
+        let mut app = headless_app();
+        app.insert_resource(ReplayMode::Record(replay_path.clone()))
+            .insert_resource(Game { start_level: GameLevel::SpaceTwo, ..default() });
+        app.finish();
+        app.cleanup();
+        for _ in 0..120 {
+            app.update();
+        }
+        app.world.send_event(AppExit);
+        app.update();
+
+        let mut app = headless_app();
+        app.insert_resource(ReplayMode::Playback(replay_path.clone()));
+        app.finish();
+        app.cleanup();
+        for _ in 0..120 {
+            app.update();
+        }
+        let _ = std::fs::remove_file(&replay_path);
+        assert_eq!(app.world.resource::<State<GameState>>().get(), &GameState::Playing);
+        assert_eq!(app.world.resource::<State<GameLevel>>().get(), &GameLevel::SpaceTwo);
+    }
+}
@@ -0,0 +1,218 @@
+use bevy::{math::bounding::{Aabb2d, BoundingCircle, BoundingVolume, IntersectsVolume}, prelude::*};
+
+// Closest point searches between a capsule and a box settle well within this many steps
+const CAPSULE_BOX_STEPS: usize = 4;
+
+/// The shape an entity collides with, centred on its `Transform` and scaled by it.
+/// Capsules also turn with it, boxes stay axis aligned.
+/// Sizes are authored from the sprite sizes in assets/, usually a bit smaller than
+/// the sprite so grazing shots do not count.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub enum Hitbox {
+    Circle { radius: f32 },
+    Aabb { half_size: Vec2 },
+    // The segment from -half_segment to half_segment, thickened by radius
+    Capsule { radius: f32, half_segment: Vec2 },
+}
+impl Hitbox {
+    /// A circle filling `fraction` of the smaller side of a sprite.
+    pub fn circle_for_sprite(size: Vec2, fraction: f32) -> Hitbox {
+        Hitbox::Circle { radius: size.min_element() / 2. * fraction }
+    }
+
+    /// A box `fraction` the size of a sprite.
+    pub fn aabb_for_sprite(size: Vec2, fraction: f32) -> Hitbox {
+        Hitbox::Aabb { half_size: size / 2. * fraction }
+    }
+
+    /// A capsule along the longer side of a sprite, `fraction` of its size.
+    pub fn capsule_for_sprite(size: Vec2, fraction: f32) -> Hitbox {
+        let size = size * fraction;
+        let radius = size.min_element() / 2.;
+        let half_segment = if size.x > size.y {
+            Vec2::new(size.x / 2. - radius, 0.)
+        } else {
+            Vec2::new(0., size.y / 2. - radius)
+        };
+        Hitbox::Capsule { radius, half_segment }
+    }
+
+    fn shape(&self, transform: &Transform) -> Shape {
+        let center = transform.translation.truncate();
+        let scale = transform.scale.truncate();
+        match *self {
+            Hitbox::Circle { radius } => Shape::Circle(BoundingCircle::new(center, radius * scale.max_element())),
+            Hitbox::Aabb { half_size } => Shape::Aabb(Aabb2d::new(center, half_size * scale)),
+            Hitbox::Capsule { radius, half_segment } => {
+                let half_segment = (transform.rotation * (half_segment * scale).extend(0.)).truncate();
+                Shape::Capsule {
+                    a: center - half_segment,
+                    b: center + half_segment,
+                    radius: radius * scale.max_element(),
+                }
+            }
+        }
+    }
+
+    /// A box around the hitbox, for the broadphase.
+    pub fn bounds(&self, transform: &Transform) -> Aabb2d {
+        match self.shape(transform) {
+            Shape::Circle(circle) => circle.aabb_2d(),
+            Shape::Aabb(aabb) => aabb,
+            Shape::Capsule { a, b, radius } => Aabb2d {
+                min: a.min(b) - radius,
+                max: a.max(b) + radius,
+            },
+        }
+    }
+
+    pub fn intersects(&self, transform: &Transform, other: &Hitbox, other_transform: &Transform) -> bool {
+        self.shape(transform).intersects(&other.shape(other_transform))
+    }
+}
+
+// A hitbox placed in the world
+enum Shape {
+    Circle(BoundingCircle),
+    Aabb(Aabb2d),
+    Capsule { a: Vec2, b: Vec2, radius: f32 },
+}
+impl Shape {
+    fn intersects(&self, other: &Shape) -> bool {
+        match (self, other) {
+            (Shape::Circle(a), Shape::Circle(b)) => a.intersects(b),
+            (Shape::Circle(circle), Shape::Aabb(aabb)) | (Shape::Aabb(aabb), Shape::Circle(circle)) => circle.intersects(aabb),
+            (Shape::Aabb(a), Shape::Aabb(b)) => a.intersects(b),
+            (Shape::Capsule { a, b, radius }, Shape::Circle(circle)) | (Shape::Circle(circle), Shape::Capsule { a, b, radius }) => {
+                closest_on_segment(*a, *b, circle.center).distance(circle.center) <= radius + circle.radius()
+            }
+            (Shape::Capsule { a, b, radius }, Shape::Aabb(aabb)) | (Shape::Aabb(aabb), Shape::Capsule { a, b, radius }) => {
+                // Bounces between the closest point on the segment and on the box
+                let mut on_segment = closest_on_segment(*a, *b, aabb.center());
+                let mut on_box = aabb.closest_point(on_segment);
+                for _ in 1..CAPSULE_BOX_STEPS {
+                    on_segment = closest_on_segment(*a, *b, on_box);
+                    on_box = aabb.closest_point(on_segment);
+                }
+                on_segment.distance(on_box) <= *radius
+            }
+            (Shape::Capsule { a, b, radius }, Shape::Capsule { a: c, b: d, radius: other_radius }) => {
+                segment_distance(*a, *b, *c, *d) <= radius + other_radius
+            }
+        }
+    }
+}
+
+fn closest_on_segment(a: Vec2, b: Vec2, point: Vec2) -> Vec2 {
+    let ab = b - a;
+    let length_squared = ab.length_squared();
+    if length_squared == 0. {
+        return a;
+    }
+    a + ab * ((point - a).dot(ab) / length_squared).clamp(0., 1.)
+}
+
+fn segment_distance(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> f32 {
+    let crosses = {
+        let side = |p: Vec2, q: Vec2, r: Vec2| (q - p).perp_dot(r - p);
+        side(a, b, c) * side(a, b, d) < 0. && side(c, d, a) * side(c, d, b) < 0.
+    };
+    if crosses {
+        return 0.;
+    }
+    // Otherwise the closest pair has one of the four end points in it
+    [
+        closest_on_segment(c, d, a).distance(a),
+        closest_on_segment(c, d, b).distance(b),
+        closest_on_segment(a, b, c).distance(c),
+        closest_on_segment(a, b, d).distance(d),
+    ].into_iter().fold(f32::MAX, f32::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    use super::*;
+
+    fn at(x: f32, y: f32) -> Transform {
+        Transform::from_xyz(x, y, 0.)
+    }
+
+    fn turned(x: f32, y: f32, angle: f32) -> Transform {
+        at(x, y).with_rotation(Quat::from_rotation_z(angle))
+    }
+
+    const CIRCLE: Hitbox = Hitbox::Circle { radius: 5. };
+    const SMALL_CIRCLE: Hitbox = Hitbox::Circle { radius: 3. };
+    const BOX: Hitbox = Hitbox::Aabb { half_size: Vec2::new(5., 5.) };
+    const SMALL_BOX: Hitbox = Hitbox::Aabb { half_size: Vec2::new(2., 2.) };
+    // Upright, from (0, -10) to (0, 10) before it is placed
+    const CAPSULE: Hitbox = Hitbox::Capsule { radius: 2., half_segment: Vec2::new(0., 10.) };
+
+    #[test]
+    fn shape_pairs() {
+        let cases = [
+            ("circles touching", CIRCLE, at(0., 0.), CIRCLE, at(10., 0.), true),
+            ("circles separated", CIRCLE, at(0., 0.), CIRCLE, at(10.5, 0.), false),
+            ("circle touching box side", CIRCLE, at(0., 0.), BOX, at(10., 0.), true),
+            ("circle clear of box side", CIRCLE, at(0., 0.), BOX, at(10.5, 0.), false),
+            ("circle over box corner", CIRCLE, at(0., 0.), BOX, at(8., 8.), true),
+            // The bounds overlap, the corner is still out of reach
+            ("circle clear of box corner", CIRCLE, at(0., 0.), BOX, at(9., 9.), false),
+            ("boxes touching", BOX, at(0., 0.), BOX, at(10., 0.), true),
+            ("boxes separated", BOX, at(0., 0.), BOX, at(10.5, 0.), false),
+            ("capsule side touching circle", CAPSULE, at(0., 0.), SMALL_CIRCLE, at(5., 0.), true),
+            ("capsule end touching circle", CAPSULE, at(0., 0.), SMALL_CIRCLE, at(0., 15.), true),
+            ("circle past capsule end", CAPSULE, at(0., 0.), SMALL_CIRCLE, at(5., 13.), false),
+            ("turned capsule reaching circle", CAPSULE, turned(0., 0., FRAC_PI_2), SMALL_CIRCLE, at(14., 0.), true),
+            ("turned capsule clear of circle", CAPSULE, turned(0., 0., FRAC_PI_2), SMALL_CIRCLE, at(0., 10.), false),
+            ("capsule touching box", CAPSULE, at(0., 0.), SMALL_BOX, at(4., 0.), true),
+            ("capsule clear of box", CAPSULE, at(0., 0.), SMALL_BOX, at(4.5, 0.), false),
+            ("upright capsule clear of box on diagonal", CAPSULE, at(0., 0.), SMALL_BOX, at(-8., 8.), false),
+            ("turned capsule reaching box on diagonal", CAPSULE, turned(0., 0., FRAC_PI_4), SMALL_BOX, at(-8., 8.), true),
+            ("capsules side by side touching", CAPSULE, at(0., 0.), CAPSULE, at(4., 0.), true),
+            ("capsules side by side separated", CAPSULE, at(0., 0.), CAPSULE, at(4.5, 0.), false),
+            ("capsules crossing", CAPSULE, at(0., 0.), CAPSULE, turned(0., 0., FRAC_PI_2), true),
+            ("turned capsule above end in reach", CAPSULE, at(0., 0.), CAPSULE, turned(0., 13.5, FRAC_PI_2), true),
+            ("turned capsule above end out of reach", CAPSULE, at(0., 0.), CAPSULE, turned(0., 14.5, FRAC_PI_2), false),
+        ];
+        for (name, hitbox, transform, other, other_transform, expected) in cases {
+            assert_eq!(hitbox.intersects(&transform, &other, &other_transform), expected, "{}", name);
+            assert_eq!(other.intersects(&other_transform, &hitbox, &transform), expected, "{} (swapped)", name);
+        }
+    }
+
+    #[test]
+    fn scaled_hitboxes() {
+        let scaled = at(0., 0.).with_scale(Vec3::splat(2.));
+        assert!(CIRCLE.intersects(&scaled, &CIRCLE, &at(15., 0.)));
+        assert!(!CIRCLE.intersects(&at(0., 0.), &CIRCLE, &at(15., 0.)));
+        assert!(CAPSULE.intersects(&scaled, &SMALL_CIRCLE, &at(0., 27.)));
+        assert!(!CAPSULE.intersects(&at(0., 0.), &SMALL_CIRCLE, &at(0., 27.)));
+    }
+
+    #[test]
+    fn segment_distances() {
+        let cases = [
+            ("crossing", Vec2::new(-1., 0.), Vec2::new(1., 0.), Vec2::new(0., -1.), Vec2::new(0., 1.), 0.),
+            ("parallel", Vec2::new(0., 0.), Vec2::new(0., 10.), Vec2::new(3., 0.), Vec2::new(3., 10.), 3.),
+            ("in line, apart", Vec2::new(0., 0.), Vec2::new(0., 10.), Vec2::new(0., 12.), Vec2::new(0., 20.), 2.),
+            ("end to side", Vec2::new(0., 0.), Vec2::new(0., 10.), Vec2::new(-5., 14.), Vec2::new(5., 14.), 4.),
+            ("end meeting end", Vec2::new(0., 0.), Vec2::new(0., 10.), Vec2::new(0., 10.), Vec2::new(5., 10.), 0.),
+            ("points", Vec2::new(0., 0.), Vec2::new(0., 0.), Vec2::new(3., 4.), Vec2::new(3., 4.), 5.),
+            ("point to segment", Vec2::new(3., 4.), Vec2::new(3., 4.), Vec2::new(0., -10.), Vec2::new(0., 10.), 3.),
+        ];
+        for (name, a, b, c, d, expected) in cases {
+            assert!((segment_distance(a, b, c, d) - expected).abs() < 1e-5, "{}", name);
+            assert!((segment_distance(c, d, a, b) - expected).abs() < 1e-5, "{} (swapped)", name);
+        }
+    }
+
+    #[test]
+    fn bounds_cover_turned_capsule() {
+        let bounds = CAPSULE.bounds(&turned(0., 0., FRAC_PI_2));
+        assert!((bounds.min - Vec2::new(-12., -2.)).length() < 1e-4);
+        assert!((bounds.max - Vec2::new(12., 2.)).length() < 1e-4);
+    }
+}
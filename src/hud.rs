@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+
+use crate::{level::Wave, player::{Player, PLAYER_MAX_HEALTH, PLAYER_MAX_SHIELD}, Game, GameLevel, GameState};
+
+pub struct HudPlugin;
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(Startup, spawn_hud)
+        .add_systems(OnEnter(GameState::MainMenu), hide_hud)
+        .add_systems(OnExit(GameState::MainMenu), show_hud)
+        .add_systems(
+            Update,
+            (
+                update_player_bars,
+                update_weapons,
+                (update_score).run_if(resource_changed::<Game>),
+                (update_wave).run_if(state_changed::<Wave>.or_else(state_changed::<GameLevel>)),
+            )
+        )
+        ;
+    }
+}
+
+const HUD_TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const HUD_FONT_SIZE: f32 = 20.;
+const HEALTH_BAR_COLOR: Color = Color::rgb(0.2, 0.8, 0.3);
+const SHIELD_BAR_COLOR: Color = Color::rgb(0.2, 0.5, 0.95);
+
+#[derive(Component, Clone, Copy)]
+enum HudBar {
+    Health,
+    Shield,
+}
+
+// Only shown during a run
+#[derive(Component)]
+struct HudRoot;
+
+#[derive(Component)]
+struct ScoreText;
+
+#[derive(Component)]
+struct WaveText;
+
+#[derive(Component)]
+struct WeaponsText;
+
+fn hud_text(text: &str) -> TextBundle {
+    TextBundle::from_section(
+        text,
+        TextStyle { font_size: HUD_FONT_SIZE, color: HUD_TEXT_COLOR, ..default() },
+    )
+}
+
+fn spawn_bar(parent: &mut ChildBuilder, bar: HudBar, color: Color) {
+    parent.spawn(NodeBundle {
+        style: Style {
+            width: Val::Px(200.),
+            height: Val::Px(10.),
+            ..default()
+        },
+        background_color: Color::rgba(1., 1., 1., 0.2).into(),
+        ..default()
+    }).with_children(|parent| {
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                background_color: color.into(),
+                ..default()
+            },
+            bar,
+        ));
+    });
+}
+
+/// Everything is filled in by the update systems, which all run on the first frame.
+fn spawn_hud(mut commands: Commands) {
+    // Score and wave along the top, leaving the middle to the boss health bar
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.),
+                left: Val::Px(10.),
+                right: Val::Px(10.),
+                justify_content: JustifyContent::SpaceBetween,
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        HudRoot,
+    )).with_children(|parent| {
+        parent.spawn((hud_text(""), ScoreText));
+        parent.spawn((hud_text(""), WaveText));
+    });
+    // Ship status along the bottom
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.),
+                left: Val::Px(10.),
+                right: Val::Px(10.),
+                justify_content: JustifyContent::SpaceBetween,
+                align_items: AlignItems::End,
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        HudRoot,
+    )).with_children(|parent| {
+        parent.spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.),
+                ..default()
+            },
+            ..default()
+        }).with_children(|parent| {
+            spawn_bar(parent, HudBar::Shield, SHIELD_BAR_COLOR);
+            spawn_bar(parent, HudBar::Health, HEALTH_BAR_COLOR);
+        });
+        parent.spawn((hud_text(""), WeaponsText));
+    });
+}
+
+fn hide_hud(mut hud_query: Query<&mut Visibility, With<HudRoot>>) {
+    for mut visibility in hud_query.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn show_hud(mut hud_query: Query<&mut Visibility, With<HudRoot>>) {
+    for mut visibility in hud_query.iter_mut() {
+        *visibility = Visibility::Inherited;
+    }
+}
+
+fn update_player_bars(
+    player_query: Query<&Player, Changed<Player>>,
+    mut bar_query: Query<(&HudBar, &mut Style)>,
+) {
+    let Ok(player) = player_query.get_single() else {
+        return;
+    };
+    for (bar, mut style) in bar_query.iter_mut() {
+        let fraction = match bar {
+            HudBar::Health => player.health / PLAYER_MAX_HEALTH,
+            HudBar::Shield => player.shield / PLAYER_MAX_SHIELD,
+        };
+        style.width = Val::Percent(fraction.clamp(0., 1.) * 100.);
+    }
+}
+
+fn update_weapons(
+    player_query: Query<&Player, Changed<Player>>,
+    mut text_query: Query<&mut Text, With<WeaponsText>>,
+) {
+    let Ok(player) = player_query.get_single() else {
+        return;
+    };
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!(
+            "L: {:?}  F: {:?}  R: {:?}",
+            player.left_weapon, player.front_weapon, player.right_weapon
+        );
+    }
+}
+
+fn update_score(
+    game: Res<Game>,
+    mut text_query: Query<&mut Text, With<ScoreText>>,
+) {
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!("Score: {}", game.score);
+    }
+}
+
+fn update_wave(
+    wave: Res<State<Wave>>,
+    level: Res<State<GameLevel>>,
+    mut text_query: Query<&mut Text, With<WaveText>>,
+) {
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!("{:?} - Wave {:?}", level.get(), wave.get());
+    }
+}
@@ -0,0 +1,211 @@
+use std::fs;
+
+use bevy::{input::InputSystem, prelude::*, utils::{HashMap, HashSet}};
+use serde::{Deserialize, Serialize};
+
+pub struct KeymapPlugin;
+impl Plugin for KeymapPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<ActionState>()
+        .init_resource::<Rebinding>()
+        .add_event::<StartRebindEvent>()
+        .add_systems(PreStartup, load_keymap)
+        .add_systems(
+            PreUpdate,
+            (start_rebind, rebind_action, update_action_state)
+                .chain()
+                .in_set(ActionSet)
+                .after(InputSystem)
+        )
+        ;
+    }
+}
+
+// Saved next to wherever the game is run from
+pub const KEYMAP_PATH: &str = "keymap.ron";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Fire,
+    AltFire,
+    Focus,
+    Pause,
+    Confirm,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    // Matches the button on any connected gamepad
+    Gamepad(GamepadButtonType),
+}
+
+/// Which keys and buttons trigger each action. Several bindings can share an action,
+/// and one binding can trigger several actions.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct Keymap {
+    pub bindings: HashMap<InputAction, Vec<InputBinding>>,
+}
+impl Default for Keymap {
+    fn default() -> Self {
+        use InputAction::*;
+        use InputBinding::*;
+        // Sticks drive movement and aim on a gamepad, see player_input.rs
+        Keymap {
+            bindings: HashMap::from([
+                (MoveUp, vec![Key(KeyCode::ArrowUp), Key(KeyCode::KeyW), Gamepad(GamepadButtonType::DPadUp)]),
+                (MoveDown, vec![Key(KeyCode::ArrowDown), Key(KeyCode::KeyS), Gamepad(GamepadButtonType::DPadDown)]),
+                (MoveLeft, vec![Key(KeyCode::ArrowLeft), Key(KeyCode::KeyA), Gamepad(GamepadButtonType::DPadLeft)]),
+                (MoveRight, vec![Key(KeyCode::ArrowRight), Key(KeyCode::KeyD), Gamepad(GamepadButtonType::DPadRight)]),
+                // The front weapon and the canons fire together on the keyboard,
+                // each trigger fires one of them on a gamepad
+                (Fire, vec![Key(KeyCode::Space), Mouse(MouseButton::Left), Gamepad(GamepadButtonType::RightTrigger2)]),
+                (AltFire, vec![Key(KeyCode::Space), Mouse(MouseButton::Left), Gamepad(GamepadButtonType::LeftTrigger2)]),
+                (Focus, vec![Key(KeyCode::ShiftLeft), Gamepad(GamepadButtonType::LeftTrigger)]),
+                (Pause, vec![Key(KeyCode::Escape), Gamepad(GamepadButtonType::Start)]),
+                (Confirm, vec![Key(KeyCode::Enter), Gamepad(GamepadButtonType::South)]),
+            ]),
+        }
+    }
+}
+impl Keymap {
+    /// Actions missing from the saved keymap (e.g. added since it was saved) keep their defaults.
+    pub fn load() -> Result<Keymap, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(KEYMAP_PATH)?;
+        let saved: Keymap = ron::from_str(&contents)?;
+        let mut keymap = Keymap::default();
+        keymap.bindings.extend(saved.bindings);
+        Ok(keymap)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(KEYMAP_PATH, contents)?;
+        Ok(())
+    }
+
+    /// Replaces the action's bindings on the new binding's device with it.
+    /// Binding a key leaves the gamepad bindings alone, and the other way round.
+    pub fn rebind(&mut self, action: InputAction, binding: InputBinding) {
+        let bindings = self.bindings.entry(action).or_default();
+        bindings.retain(|existing| existing.is_gamepad() != binding.is_gamepad());
+        bindings.push(binding);
+    }
+}
+
+impl InputBinding {
+    pub fn is_gamepad(&self) -> bool {
+        matches!(self, InputBinding::Gamepad(_))
+    }
+}
+
+/// Which actions are held, and which were pressed this frame.
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    pressed: HashSet<InputAction>,
+    just_pressed: HashSet<InputAction>,
+}
+impl ActionState {
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.just_pressed.contains(&action)
+    }
+}
+
+/// Updated in `PreUpdate`, so anything after it sees this frame's actions.
+#[derive(SystemSet, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ActionSet;
+
+/// Sent to bind the next key, mouse or gamepad button pressed to an action.
+#[derive(Event)]
+pub struct StartRebindEvent(pub InputAction);
+
+/// The action waiting on a new binding, if any.
+#[derive(Resource, Default)]
+pub struct Rebinding(pub Option<InputAction>);
+
+fn load_keymap(mut commands: Commands) {
+    let keymap = match Keymap::load() {
+        Ok(keymap) => keymap,
+        Err(err) => {
+            info!("using the default keymap, could not load {}: {}", KEYMAP_PATH, err);
+            Keymap::default()
+        }
+    };
+    commands.insert_resource(keymap);
+}
+
+fn start_rebind(
+    mut events: EventReader<StartRebindEvent>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    for event in events.read() {
+        rebinding.0 = Some(event.0);
+    }
+}
+
+fn rebind_action(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut keymap: ResMut<Keymap>,
+) {
+    let Some(action) = rebinding.0 else {
+        return;
+    };
+    let binding = keyboard_input.get_just_pressed().next().map(|key| InputBinding::Key(*key))
+        .or_else(|| mouse_buttons.get_just_pressed().next().map(|button| InputBinding::Mouse(*button)))
+        .or_else(|| gamepad_buttons.get_just_pressed().next().map(|button| InputBinding::Gamepad(button.button_type)));
+    if let Some(binding) = binding {
+        keymap.rebind(action, binding);
+        rebinding.0 = None;
+        if let Err(err) = keymap.save() {
+            error!("could not save keymap to {}: {}", KEYMAP_PATH, err);
+        }
+    }
+}
+
+fn update_action_state(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    keymap: Res<Keymap>,
+    rebinding: Res<Rebinding>,
+    mut action_state: ResMut<ActionState>,
+) {
+    action_state.pressed.clear();
+    action_state.just_pressed.clear();
+    // The press that finished a rebind should not also trigger the action
+    if rebinding.is_changed() {
+        return;
+    }
+    for (action, bindings) in keymap.bindings.iter() {
+        for binding in bindings {
+            let (pressed, just_pressed) = match binding {
+                InputBinding::Key(key) => (keyboard_input.pressed(*key), keyboard_input.just_pressed(*key)),
+                InputBinding::Mouse(button) => (mouse_buttons.pressed(*button), mouse_buttons.just_pressed(*button)),
+                InputBinding::Gamepad(button_type) => (
+                    gamepads.iter().any(|gamepad| gamepad_buttons.pressed(GamepadButton::new(gamepad, *button_type))),
+                    gamepads.iter().any(|gamepad| gamepad_buttons.just_pressed(GamepadButton::new(gamepad, *button_type))),
+                ),
+            };
+            if pressed {
+                action_state.pressed.insert(*action);
+            }
+            if just_pressed {
+                action_state.just_pressed.insert(*action);
+            }
+        }
+    }
+}
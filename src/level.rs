@@ -1,6 +1,7 @@
-use bevy::prelude::*;
+use bevy::{asset::LoadState, prelude::*, utils::HashMap};
+use serde::Deserialize;
 
-use crate::{basic_enemy::BasicEnemy, basic_enemy_move_patterns::EnemyMovePattern, beam::{Beam, BeamType, ShootPattern, ShootType}, canon::ShootTimer, enemy_core::{EnemyCore, EnemyCoreBuilder, EnemyCoreBundle, EnemyType, SpawnEnemyEvent}};
+use crate::{basic_enemy::check_collision, boss::BossDefeatedEvent, enemy_core::{spawn_enemy, SpawnEnemyEvent}, level_waves::{level_waves_path, LevelWaves, LevelWavesLoader, WaveSpawn}, GameLevel, GameState};
 
 pub struct LevelPlugin;
 impl Plugin for LevelPlugin {
@@ -8,21 +9,38 @@ impl Plugin for LevelPlugin {
        app
         .init_state::<Wave>()
         .init_state::<WaveState>()
-        .insert_resource(SpawnTimer(Timer::from_seconds(0.1, TimerMode::Repeating)))
+        .init_asset::<LevelWaves>()
+        .init_asset_loader::<LevelWavesLoader>()
+        .add_systems(Startup, load_level_waves)
         .add_systems(OnTransition {from: WaveState::Active, to: WaveState::Completed}, increment_state)
-        .add_systems(Update, (check_wave_complete).run_if(in_state(WaveState::Active)))
-        .add_systems(OnEnter(Wave::One), wave_one)
-        .add_systems(OnEnter(Wave::Two), wave_two)
-        .add_systems(OnEnter(Wave::Three), wave_three)
-        .add_systems(OnEnter(Wave::Four), wave_four)
-        .add_systems(Update, (spawn_horde).run_if(in_state(Wave::Three)))
+        .add_systems(OnExit(GameState::GameOver), clear_wave)
+        // Only a restart drops an active wave back to the start
+        .add_systems(OnTransition {from: WaveState::Active, to: WaveState::Initialized}, clear_wave)
+        .add_systems(Update, (finish_loading).run_if(in_state(GameState::Loading)))
+        .add_systems(
+            FixedUpdate,
+            (start_wave)
+                .run_if(in_state(GameState::Playing))
+                .run_if(in_state(WaveState::Initialized))
+        )
+        .add_systems(FixedUpdate, 
+            (tick_pending_spawns)
+                .before(spawn_enemy)
+                .run_if(in_state(GameState::Playing))
+        )
+        // Reads the BossDefeatedEvent check_collision sends in the same step
+        .add_systems(
+            FixedUpdate,
+            (finish_boss_wave)
+                .after(check_collision)
+                .before(check_wave_complete)
+                .run_if(in_state(GameState::Playing))
+        )
+        .add_systems(FixedUpdate, (check_wave_complete).after(spawn_enemy).run_if(in_state(WaveState::Active)))
        ;
     }
 }
 
-// We will want to lock out some time between waves
-#[derive(Component, Resource)]
-struct WaveTimer(Timer);
 // Used to tell if we are currently in a state
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
 pub enum WaveState {
@@ -33,9 +51,8 @@ pub enum WaveState {
     // GameOver,
 }
 
-// Todo, have struct of waves/states w/ enemy numbers (and when) to spawn,
-// cooldown timer.
-#[derive(Clone, Component, Eq, PartialEq, Debug, Hash, Default, States)]
+// What each wave spawns is described in the level's wave file (see level_waves.rs)
+#[derive(Clone, Component, Eq, PartialEq, Debug, Hash, Default, States, Deserialize)]
 pub enum Wave {
     #[default]
     One,
@@ -64,13 +81,19 @@ impl Wave {
 fn increment_state(
     state: Res<State<Wave>>,
     mut next_state: ResMut<NextState<Wave>>,
-    mut next_wave_state: ResMut<NextState<WaveState>>
+    mut next_wave_state: ResMut<NextState<WaveState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
 ) {
     match state.get() {
         Wave::None => {},
+        // The boss was the level's last wave, see level_complete.rs for what comes next
+        Wave::Boss => {
+            next_state.set(Wave::None);
+            next_game_state.set(GameState::LevelComplete);
+        }
         _ => {
             next_state.set(state.next());
-            next_wave_state.set(WaveState::Active);
+            next_wave_state.set(WaveState::Initialized);
         }
     }
 }
@@ -83,145 +106,120 @@ fn check_wave_complete(
     }
 }
 
-
-fn wave_one(
-    mut commands: Commands, 
-    asset_server: Res<AssetServer>, 
-    mut next_wave_state: ResMut<NextState<WaveState>>,
-    mut spawn_enemy_event_writer: EventWriter<SpawnEnemyEvent>
+/// Beating the boss ends the level, so whatever else is left of its wave goes with it.
+fn finish_boss_wave(
+    mut commands: Commands,
+    mut boss_defeated_events: EventReader<BossDefeatedEvent>,
+    wave_query: Query<Entity, With<Wave>>,
 ) {
-    next_wave_state.set(WaveState::Active); 
-    spawn_enemy_event_writer.send(SpawnEnemyEvent((
-        EnemyCoreBundle {
-            enemy_core: EnemyCore::builder()
-                .direction(1., 0.)
-                .shoot(true)
-                .shoot_type(ShootType::Basic)
-                .build()
-        },
-        EnemyType::Basic,
-        Transform::from_xyz(60., 300., 0.),
-        Wave::One,
-    )));
-    spawn_enemy_event_writer.send(SpawnEnemyEvent((
-        EnemyCoreBundle {
-            enemy_core: EnemyCore::builder()
-                .direction(1., 0.)
-                .shoot(true)
-                .shoot_type(ShootType::Basic)
-                .build()
-        },
-        EnemyType::Basic,
-        Transform::from_xyz(0., 300., 0.),
-        Wave::One,
-    )));
-    spawn_enemy_event_writer.send(SpawnEnemyEvent((
-        EnemyCoreBundle {
-            enemy_core: EnemyCore::builder()
-                .direction(1., 0.)
-                .shoot(true)
-                .shoot_type(ShootType::Basic)
-                .build()
-        },
-        EnemyType::Basic,
-        Transform::from_xyz(-60., 300., 0.),
-        Wave::One,
-    )));
+    if boss_defeated_events.read().count() > 0 {
+        for entity in wave_query.iter() {
+            commands.entity(entity).despawn();
+        }
+    }
 }
-fn wave_two (
-    mut spawn_enemy_event_writer: EventWriter<SpawnEnemyEvent>
-) {
-    let shoot_pat =  ShootPattern::flat_spray();
-    spawn_enemy_event_writer.send(SpawnEnemyEvent((
-        EnemyCoreBundle {
-            enemy_core: EnemyCore::builder()
-                            .move_pattern(EnemyMovePattern::EnterShoot)
-                            .direction(1., 0.)
-                            .shoot(true)
-                            .shoot_type(ShootType::TestHell(shoot_pat))
-                            .shoot_timer(crate::enemy_core::ShootTimer(Timer::from_seconds(0.1, TimerMode::Repeating)))
-                            .build()
-        },
-        EnemyType::EnemyB,
-        Transform::from_xyz(0., 500., 0.),
-        Wave::Two,
-    )));
 
-    spawn_enemy_event_writer.send(SpawnEnemyEvent((
-        EnemyCoreBundle {
-            enemy_core: EnemyCore::builder()
-                            .direction(1., 0.)
-                            .shoot(true)
-                            .shoot_type(ShootType::TestHell(ShootPattern::line_spray()))
-                            .shoot_timer(crate::enemy_core::ShootTimer(Timer::from_seconds(0.1, TimerMode::Repeating)))
-                            .build()
-        },
-        EnemyType::EnemyB,
-        Transform::from_xyz(300., 300., 0.),
-        Wave::Two,
-    )));
+/// Clears out the enemies and queued spawns of the last run or wave.
+fn clear_wave(
+    mut commands: Commands,
+    wave_query: Query<Entity, With<Wave>>,
+) {
+    for entity in wave_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
 }
 
-
+/// The wave files of every level. They are all loaded up front, so any level can be picked to start in.
 #[derive(Resource)]
-pub struct SpawnTimer(Timer);
-#[derive(Component, Debug)]
-pub struct WaveHordeCount {
-    count: i32,
-    max: i32,
-}
-impl WaveHordeCount {
-    pub fn new(max: i32) -> Self {
-        WaveHordeCount {
-            count: 0,
-            max: max,
-        }
+pub struct LevelWavesHandles(pub HashMap<GameLevel, Handle<LevelWaves>>);
+impl LevelWavesHandles {
+    pub fn get<'a>(&self, level_waves: &'a Assets<LevelWaves>, level: &GameLevel) -> Option<&'a LevelWaves> {
+        self.0.get(level).and_then(|handle| level_waves.get(handle))
     }
 }
-fn wave_three (
-    mut commands: Commands, 
-    mut spawn_timer: ResMut<SpawnTimer>, 
-    mut next_wave_state: ResMut<NextState<WaveState>>
+
+fn load_level_waves(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
 ) {
-    next_wave_state.set(WaveState::Active); //could schedule this
-    commands.spawn(WaveHordeCount::new(100));
-    spawn_timer.0.reset();
+    let handles = GameLevel::ALL.iter()
+        .map(|level| (*level, asset_server.load(level_waves_path(level))))
+        .collect();
+    commands.insert_resource(LevelWavesHandles(handles));
 }
 
-pub fn spawn_horde(
-    mut commands: Commands, 
-    asset_server: Res<AssetServer>, 
-    mut spawn_timer: ResMut<SpawnTimer>,
-    mut horde_count_query: Query<&mut WaveHordeCount>,
-    time: Res<Time>,
+/// Holds the game in `GameState::Loading` until the wave files are ready, so a run
+/// always starts on the same fixed step no matter how long loading took.
+fn finish_loading(
+    asset_server: Res<AssetServer>,
+    level_waves_handles: Res<LevelWavesHandles>,
+    mut next_game_state: ResMut<NextState<GameState>>,
 ) {
-    let horde_count = horde_count_query.get_single_mut();
-    match horde_count {
-        Ok(mut horde_count) => {
-            if horde_count.count < horde_count.max {
-                if spawn_timer.0.tick(time.delta()).finished() {
+    let loaded = level_waves_handles.0.values()
+        .all(|handle| matches!(asset_server.load_state(handle), LoadState::Loaded | LoadState::Failed));
+    if loaded {
+        next_game_state.set(GameState::MainMenu);
+    }
+}
+
+/// Queues up every spawn of the current wave once the level's wave file is loaded.
+/// Each queued spawn carries the `Wave` tag, so the wave is not complete until
+/// all of them have fired and their enemies are gone.
+fn start_wave(
+    mut commands: Commands,
+    level_waves_handles: Res<LevelWavesHandles>,
+    level_waves: Res<Assets<LevelWaves>>,
+    level: Res<State<GameLevel>>,
+    wave: Res<State<Wave>>,
+    mut next_wave_state: ResMut<NextState<WaveState>>,
+) {
+    let wave = wave.get();
+    match level_waves_handles.get(&level_waves, level.get()) {
+        Some(level_waves) => {
+            if let Some(definition) = level_waves.waves.get(wave) {
+                for spawn in definition.spawns.iter() {
                     commands.spawn((
-                        SpriteBundle {
-                            texture: asset_server.load("enemy_test.png"),
-                            transform: Transform::from_xyz(0., 400., 0.),
-                            ..default()
+                        PendingSpawn {
+                            timer: Timer::from_seconds(spawn.delay, TimerMode::Once),
+                            remaining: spawn.count,
+                            spawn: spawn.clone(),
                         },
-                        BasicEnemy::new(EnemyMovePattern::Down).shoot(false).health(1.),
-                        Wave::Three,
+                        wave.clone(),
                     ));
-                    spawn_timer.0.reset();
                 }
-                horde_count.count += 1;
-            } else {
-                
             }
         }
-        Err(err) => {
-            eprintln!("err: {:?}", err);
+        None => {
+            warn!("no wave file loaded, skipping {:?}", wave);
         }
     }
+    next_wave_state.set(WaveState::Active);
+}
+
+#[derive(Component)]
+pub struct PendingSpawn {
+    timer: Timer,
+    remaining: u32,
+    spawn: WaveSpawn,
 }
 
-fn wave_four() {
-    //
-}
\ No newline at end of file
+fn tick_pending_spawns(
+    mut commands: Commands,
+    mut pending_query: Query<(&mut PendingSpawn, &Wave, Entity)>,
+    mut spawn_enemy_event_writer: EventWriter<SpawnEnemyEvent>,
+    time: Res<Time>,
+) {
+    for (mut pending, wave, entity) in pending_query.iter_mut() {
+        if pending.timer.tick(time.delta()).finished() {
+            if pending.remaining > 0 {
+                spawn_enemy_event_writer.send(pending.spawn.to_event(wave));
+                pending.remaining -= 1;
+            }
+            if pending.remaining == 0 {
+                commands.entity(entity).despawn();
+            } else {
+                pending.timer = Timer::from_seconds(pending.spawn.interval, TimerMode::Once);
+            }
+        }
+    }
+}
@@ -1,29 +1,56 @@
 use bevy::{prelude::*};
 
-use crate::GameState;
+use crate::{GameLevel, GameState};
 
 pub struct LevelBackgroundPlugin;
 impl Plugin for LevelBackgroundPlugin {
     fn build(&self, app: &mut App) {
        app
-       .add_systems(OnEnter(GameState::Playing), load_bg)
-       .add_systems(FixedUpdate, (animate_bg).run_if(in_state(GameState::Playing)));
+       .add_systems(Update, (load_bg).run_if(state_changed::<GameLevel>))
+       .add_systems(FixedUpdate, (animate_bg).run_if(not(in_state(GameState::Paused))));
     }
 }
 
 #[derive(Component)]
 struct BackgroundTile;
-//speed per second
-const TILE_SPEED: Vec2 = Vec2::new(-50., -100.);
+
+/// How a level's background looks and scrolls.
+struct BackgroundSet {
+    texture: &'static str,
+    //speed per second
+    speed: Vec2,
+}
+fn background_set(level: &GameLevel) -> BackgroundSet {
+    match level {
+        GameLevel::SpaceOne => BackgroundSet {
+            texture: "spacebg.png",
+            speed: Vec2::new(-50., -100.),
+        },
+        GameLevel::SpaceTwo => BackgroundSet {
+            texture: "redbg.png",
+            speed: Vec2::new(-20., -160.),
+        },
+    }
+}
 const TILE_X_BOUND: f32 = 1050.;
 const TILE_Y_BOUND: f32 = 650.;
 
-fn load_bg(mut commands: Commands, asset_server: Res<AssetServer>) {
+/// Swaps in the current level's tiles, e.g. when a new level starts.
+fn load_bg(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    level: Res<State<GameLevel>>,
+    tile_query: Query<Entity, With<BackgroundTile>>,
+) {
+    for entity in tile_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    let set = background_set(level.get());
     //dumb first take
     let mut pointer = Vec2::new(-TILE_X_BOUND, -TILE_Y_BOUND);
     while pointer.y < TILE_Y_BOUND {
         while pointer.x < TILE_X_BOUND {
-            render_tile(&mut commands, &asset_server, Transform::from_xyz(pointer.x, pointer.y, -1.));
+            render_tile(&mut commands, &asset_server, &set, Transform::from_xyz(pointer.x, pointer.y, -1.));
             pointer.x += 200.;
         }
         //advance y pos
@@ -36,29 +63,31 @@ fn load_bg(mut commands: Commands, asset_server: Res<AssetServer>) {
 fn animate_bg(
     mut commands: Commands, asset_server: Res<AssetServer>,
     time: Res<Time>,
+    level: Res<State<GameLevel>>,
     mut query: Query<(&mut BackgroundTile, &mut Transform, Entity)>
 ) {
+    let set = background_set(level.get());
     for (_tile, mut transform, entity) in query.iter_mut() {
         // //despawn tile if out out of view
         // //using hard-coded dims now, but should query window size or set hard window size
         if transform.translation.y <= -TILE_Y_BOUND {
             commands.entity(entity).despawn();
-            render_tile(&mut commands, &asset_server, Transform::from_xyz(transform.translation.x, TILE_Y_BOUND, -1.));
+            render_tile(&mut commands, &asset_server, &set, Transform::from_xyz(transform.translation.x, TILE_Y_BOUND, -1.));
         }
         if transform.translation.x <= -TILE_X_BOUND {
             commands.entity(entity).despawn();
-            render_tile(&mut commands, &asset_server, Transform::from_xyz(TILE_X_BOUND, transform.translation.y, -1.));
+            render_tile(&mut commands, &asset_server, &set, Transform::from_xyz(TILE_X_BOUND, transform.translation.y, -1.));
         }
-        transform.translation +=  (TILE_SPEED * time.delta_seconds()).extend(-1.);
+        transform.translation +=  (set.speed * time.delta_seconds()).extend(-1.);
         transform.translation.x = transform.translation.x.floor();
         transform.translation.y = transform.translation.y.floor();
     }
 }
 
-fn render_tile(commands: &mut Commands, asset_server: &Res<AssetServer>, transform: Transform) {
+fn render_tile(commands: &mut Commands, asset_server: &Res<AssetServer>, set: &BackgroundSet, transform: Transform) {
     commands.spawn((SpriteBundle {
-        texture: asset_server.load("spacebg.png"),
-        transform: transform,
+        texture: asset_server.load(set.texture),
+        transform,
         ..default()
     },BackgroundTile));
 }
\ No newline at end of file
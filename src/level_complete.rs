@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+
+use crate::{level::{Wave, WaveState}, GameLevel, GameState};
+
+pub struct LevelCompletePlugin;
+impl Plugin for LevelCompletePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(OnEnter(GameState::LevelComplete), spawn_banner)
+        .add_systems(OnExit(GameState::LevelComplete), despawn_banner)
+        .add_systems(FixedUpdate, (advance_level).run_if(in_state(GameState::LevelComplete)))
+        ;
+    }
+}
+
+// How long the banner stays up before the next level starts
+const LEVEL_COMPLETE_TIME: f32 = 3.;
+
+#[derive(Component)]
+struct LevelCompleteBanner(Timer);
+
+fn spawn_banner(
+    mut commands: Commands,
+    level: Res<State<GameLevel>>,
+) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        },
+        LevelCompleteBanner(Timer::from_seconds(LEVEL_COMPLETE_TIME, TimerMode::Once)),
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            format!("{:?} COMPLETE", level.get()),
+            TextStyle { font_size: 56., color: Color::rgb(0.9, 0.9, 0.9), ..default() },
+        ));
+    });
+}
+
+fn despawn_banner(
+    mut commands: Commands,
+    banner_query: Query<Entity, With<LevelCompleteBanner>>,
+) {
+    for entity in banner_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Moves on to the next level's first wave once the banner is done. The player's
+/// ship, and with it its health and weapons, stays as it is, and so does the score.
+/// After the last level the run is over.
+fn advance_level(
+    mut banner_query: Query<&mut LevelCompleteBanner>,
+    level: Res<State<GameLevel>>,
+    time: Res<Time>,
+    mut next_level: ResMut<NextState<GameLevel>>,
+    mut next_wave: ResMut<NextState<Wave>>,
+    mut next_wave_state: ResMut<NextState<WaveState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    let Ok(mut banner) = banner_query.get_single_mut() else {
+        return;
+    };
+    if !banner.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    match level.get().next() {
+        Some(level) => {
+            next_level.set(level);
+            next_wave.set(Wave::One);
+            next_wave_state.set(WaveState::Initialized);
+            next_game_state.set(GameState::Playing);
+        }
+        None => next_game_state.set(GameState::GameOver),
+    }
+}
@@ -0,0 +1,299 @@
+use bevy::{asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext}, prelude::*, utils::{BoxedFuture, HashMap}};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{basic_enemy_move_patterns::EnemyMovePattern, beam::{BeamType, ShootPattern, ShootType}, enemy_core::{EnemyCore, EnemyCoreBundle, EnemyPhase, EnemyType, ShootTimer, SpawnEnemyEvent}, enemy_path::EnemyPath, level::Wave, GameLevel};
+
+/// Every wave of a level, keyed by the `Wave` it is spawned in.
+/// Loaded from `assets/waves/<level>.waves.ron`.
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct LevelWaves {
+    pub waves: HashMap<Wave, WaveDefinition>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct WaveDefinition {
+    pub spawns: Vec<WaveSpawn>,
+    // Seconds the wave has to be cleared in for a time bonus, see score.rs
+    pub bonus_time: f32,
+}
+impl Default for WaveDefinition {
+    fn default() -> Self {
+        WaveDefinition {
+            spawns: vec![],
+            bonus_time: 30.,
+        }
+    }
+}
+
+/// A single enemy entry in a wave file. Mirrors the `EnemyCoreBuilder` options,
+/// plus when (and how many times) the enemy should be spawned.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct WaveSpawn {
+    pub enemy_type: EnemyType,
+    pub move_pattern: EnemyMovePattern,
+    pub direction: (f32, f32),
+    pub health: f32,
+    pub shoot: bool,
+    pub shoot_type: WaveShootType,
+    pub shoot_interval: f32,
+    pub position: (f32, f32),
+    // Seconds after the wave starts before the first spawn
+    pub delay: f32,
+    pub count: u32,
+    // Seconds between spawns when count > 1
+    pub interval: f32,
+    pub phases: Vec<WavePhase>,
+    // Only used by the StartShootGo move pattern
+    pub stop_position: (f32, f32),
+    pub volleys: u32,
+    pub exit_direction: (f32, f32),
+    // Sets the move pattern to Path when it has any segments
+    pub path: EnemyPath,
+}
+
+/// Takes over from the spawn's own movement and shooting once health drops to `health`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct WavePhase {
+    pub health: f32,
+    pub move_pattern: EnemyMovePattern,
+    pub shoot_type: WaveShootType,
+    pub shoot_interval: f32,
+}
+impl Default for WavePhase {
+    fn default() -> Self {
+        WavePhase {
+            health: 0.,
+            move_pattern: EnemyMovePattern::Basic,
+            shoot_type: WaveShootType::Basic,
+            shoot_interval: 0.3,
+        }
+    }
+}
+impl From<&WavePhase> for EnemyPhase {
+    fn from(phase: &WavePhase) -> Self {
+        EnemyPhase {
+            health: phase.health,
+            move_pattern: phase.move_pattern.clone(),
+            shoot_type: (&phase.shoot_type).into(),
+            shoot_timer: ShootTimer(Timer::from_seconds(phase.shoot_interval, TimerMode::Repeating)),
+        }
+    }
+}
+impl Default for WaveSpawn {
+    fn default() -> Self {
+        WaveSpawn {
+            enemy_type: EnemyType::Basic,
+            move_pattern: EnemyMovePattern::Basic,
+            direction: (0., 0.),
+            health: 100.,
+            shoot: true,
+            shoot_type: WaveShootType::Basic,
+            shoot_interval: 0.3,
+            position: (0., 0.),
+            delay: 0.,
+            count: 1,
+            interval: 0.,
+            phases: vec![],
+            stop_position: (0., 250.),
+            volleys: 3,
+            exit_direction: (0., 1.),
+            path: EnemyPath::default(),
+        }
+    }
+}
+impl WaveSpawn {
+    pub fn to_event(&self, wave: &Wave) -> SpawnEnemyEvent {
+        let mut enemy_core = EnemyCore::builder()
+            .move_pattern(self.move_pattern.clone())
+            .direction(self.direction.0, self.direction.1)
+            .health(self.health)
+            .shoot(self.shoot)
+            .shoot_type((&self.shoot_type).into())
+            .shoot_timer(ShootTimer(Timer::from_seconds(self.shoot_interval, TimerMode::Repeating)))
+            .stop_position(self.stop_position.0, self.stop_position.1)
+            .volleys(self.volleys)
+            .exit_direction(self.exit_direction.0, self.exit_direction.1);
+        if !self.path.segments.is_empty() {
+            enemy_core = enemy_core.path(self.path.clone());
+        }
+        for phase in self.phases.iter() {
+            enemy_core = enemy_core.phase(phase.into());
+        }
+        SpawnEnemyEvent((
+            EnemyCoreBundle {
+                enemy_core: enemy_core.build()
+            },
+            self.enemy_type,
+            Transform::from_xyz(self.position.0, self.position.1, 0.),
+            wave.clone(),
+        ))
+    }
+}
+
+/// The shoot types a wave file can ask for. Angles are in degrees here,
+/// and `beam_type` defaults to FireBall.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub enum WaveShootType {
+    #[default]
+    Basic,
+    FlatSpray,
+    LineSpray,
+    AimedBurst,
+    Ring {
+        #[serde(default)]
+        beam_type: BeamType,
+        count: u32,
+        speed: f32,
+    },
+    Arc {
+        #[serde(default)]
+        beam_type: BeamType,
+        count: u32,
+        speed: f32,
+        spread: f32,
+    },
+    // A ring turning by `spin` each volley
+    Spiral {
+        #[serde(default)]
+        beam_type: BeamType,
+        count: u32,
+        speed: f32,
+        spin: f32,
+    },
+    // An arc centred on the player
+    AimedFan {
+        #[serde(default)]
+        beam_type: BeamType,
+        count: u32,
+        speed: f32,
+        spread: f32,
+    },
+    Scatter {
+        #[serde(default)]
+        beam_type: BeamType,
+        count: u32,
+        speed: f32,
+        spread: f32,
+    },
+    // Turns another pattern by `spin` degrees more every volley, on top of its own spin
+    Spin {
+        shoot_type: Box<WaveShootType>,
+        spin: f32,
+    },
+    // Fires another pattern for `firing` seconds, then rests for `resting`
+    Bursts {
+        shoot_type: Box<WaveShootType>,
+        firing: f32,
+        resting: f32,
+    },
+    // Takes turns between patterns, one per volley. Only aimed if every one of them is.
+    Alternate(Vec<WaveShootType>),
+}
+impl WaveShootType {
+    // Applies `change` to the pattern underneath, Basic has none
+    fn with_pattern(&self, change: impl FnOnce(ShootPattern) -> ShootPattern) -> ShootType {
+        match ShootType::from(self) {
+            ShootType::Basic => ShootType::Basic,
+            ShootType::TestHell(pattern) => ShootType::TestHell(change(pattern)),
+            ShootType::Aimed(pattern) => ShootType::Aimed(change(pattern)),
+        }
+    }
+}
+impl From<&WaveShootType> for ShootType {
+    fn from(shoot_type: &WaveShootType) -> Self {
+        match shoot_type {
+            WaveShootType::Basic => ShootType::Basic,
+            WaveShootType::FlatSpray => ShootType::TestHell(ShootPattern::flat_spray()),
+            WaveShootType::LineSpray => ShootType::TestHell(ShootPattern::line_spray()),
+            WaveShootType::AimedBurst => ShootType::Aimed(ShootPattern::aimed_burst()),
+            WaveShootType::Ring { beam_type, count, speed } => {
+                ShootType::TestHell(ShootPattern::ring(beam_type, *count, *speed))
+            }
+            WaveShootType::Arc { beam_type, count, speed, spread } => {
+                ShootType::TestHell(ShootPattern::arc(beam_type, *count, *speed, spread.to_radians()))
+            }
+            WaveShootType::Spiral { beam_type, count, speed, spin } => {
+                ShootType::TestHell(ShootPattern::spiral(beam_type, *count, *speed, spin.to_radians()))
+            }
+            WaveShootType::AimedFan { beam_type, count, speed, spread } => {
+                ShootType::aimed_fan(beam_type, *count, *speed, spread.to_radians())
+            }
+            WaveShootType::Scatter { beam_type, count, speed, spread } => {
+                ShootType::TestHell(ShootPattern::random_scatter(beam_type, *count, *speed, spread.to_radians()))
+            }
+            WaveShootType::Spin { shoot_type, spin } => {
+                // On top of whatever the pattern spins already, so Spin can be nested
+                shoot_type.with_pattern(|pattern| {
+                    let spin = pattern.spin + spin.to_radians();
+                    pattern.spin(spin)
+                })
+            }
+            WaveShootType::Bursts { shoot_type, firing, resting } => {
+                shoot_type.with_pattern(|pattern| pattern.bursts(*firing, *resting))
+            }
+            WaveShootType::Alternate(shoot_types) => {
+                let mut aimed = !shoot_types.is_empty();
+                let mut patterns = vec![];
+                for shoot_type in shoot_types.iter() {
+                    match shoot_type.into() {
+                        ShootType::Basic => aimed = false,
+                        ShootType::TestHell(pattern) => {
+                            aimed = false;
+                            patterns.push(pattern);
+                        }
+                        ShootType::Aimed(pattern) => patterns.push(pattern),
+                    }
+                }
+                let pattern = ShootPattern::alternate(patterns);
+                if aimed { ShootType::Aimed(pattern) } else { ShootType::TestHell(pattern) }
+            }
+        }
+    }
+}
+
+pub fn level_waves_path(level: &GameLevel) -> &'static str {
+    match level {
+        GameLevel::SpaceOne => "waves/space_one.waves.ron",
+        GameLevel::SpaceTwo => "waves/space_two.waves.ron",
+    }
+}
+
+#[derive(Default)]
+pub struct LevelWavesLoader;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum LevelWavesLoaderError {
+    #[error("Could not read wave file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse wave file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for LevelWavesLoader {
+    type Asset = LevelWaves;
+    type Settings = ();
+    type Error = LevelWavesLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let level_waves = ron::de::from_bytes::<LevelWaves>(&bytes)?;
+            Ok(level_waves)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["waves.ron"]
+    }
+}
@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+pub mod basic_enemy;
+pub mod basic_enemy_move_patterns;
+pub mod enemy_core;
+pub mod enemy_path;
+pub mod game_over;
+pub mod ghost_orchid;
+pub mod camera;
+pub mod canon;
+pub mod collision_core;
+pub mod explosion_core;
+pub mod headless;
+pub mod hitbox;
+pub mod hud;
+pub mod keymap;
+pub mod player;
+pub mod level;
+pub mod level_waves;
+pub mod main_menu;
+pub mod menu;
+pub mod pause_menu;
+pub mod player_input;
+pub mod projectile_pool;
+pub mod replay;
+pub mod rose_gunner;
+pub mod score;
+pub mod beam;
+pub mod boss;
+pub mod shoot_patterns;
+pub mod level_background;
+pub mod level_complete;
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
+pub enum GameState {
+    #[default]
+    Loading,
+    MainMenu,
+    Playing,
+    Paused,
+    LevelComplete,
+    GameOver,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Default, States, Serialize, Deserialize)]
+pub enum GameLevel {
+    #[default]
+    SpaceOne,
+    SpaceTwo,
+}
+impl GameLevel {
+    pub const ALL: [GameLevel; 2] = [GameLevel::SpaceOne, GameLevel::SpaceTwo];
+
+    /// The level after this one, if there is one.
+    pub fn next(&self) -> Option<GameLevel> {
+        match self {
+            GameLevel::SpaceOne => Some(GameLevel::SpaceTwo),
+            GameLevel::SpaceTwo => None,
+        }
+    }
+}
+
+/// How forgiving a run is. Picked with `--difficulty` and saved in replays.
+#[derive(Resource, Clone, Copy, Eq, PartialEq, Debug, Hash, Default, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+}
+
+/// The current run. Reset when a new run starts.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Game {
+    pub score: i32,
+    // Where the run began, playing again starts from here
+    pub start_level: GameLevel,
+}
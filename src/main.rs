@@ -1,33 +1,74 @@
 use bevy::{prelude::*, window::WindowResolution};
-mod basic_enemy;
-mod basic_enemy_move_patterns;
-mod enemy_core;
-pub mod camera;
-mod canon;
-mod collision_core;
-pub mod explosion_core;
-pub mod player;
-mod level;
-pub mod beam;
-pub mod shoot_patterns;
-mod level_background;
-
-#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
-pub enum GameState {
-    #[default]
-    Playing,
-    Paused,
-    // GameOver,
-}
-
-#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
-pub enum GameLevel {
-    #[default]
-    SpaceOne,
-    SpaceTwo,
-}
+use first_game::{
+    basic_enemy,
+    beam,
+    boss,
+    camera,
+    collision_core,
+    enemy_core,
+    explosion_core,
+    game_over,
+    ghost_orchid,
+    headless,
+    hud,
+    keymap,
+    level,
+    level_background,
+    level_complete,
+    main_menu,
+    menu,
+    pause_menu,
+    player,
+    player_input,
+    projectile_pool,
+    replay::{self, ReplayMode},
+    rose_gunner,
+    score,
+    Difficulty,
+    Game,
+    GameLevel,
+    GameState,
+};
 
 fn main() {
+    // `--headless [frames]` steps the game without a window, e.g. on CI machines.
+    // `--record <file>` saves the run's input, `--replay <file>` plays it back from the level it started on.
+    // `--difficulty <easy|normal|hard>` picks the difficulty, a replay uses the one it was recorded on.
+    let mut headless_frames = None;
+    let mut replay_mode = ReplayMode::Off;
+    let mut difficulty = Difficulty::default();
+    let mut args = std::env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--headless" => {
+                let frames = args.next_if(|frames| frames.parse::<u32>().is_ok());
+                headless_frames = Some(frames.map_or(600, |frames| frames.parse().unwrap()));
+            }
+            "--difficulty" => {
+                let name = args.next().unwrap_or_default();
+                match Difficulty::ALL.into_iter().find(|d| format!("{:?}", d).eq_ignore_ascii_case(&name)) {
+                    Some(picked) => difficulty = picked,
+                    None => eprintln!("unknown difficulty: {}", name),
+                }
+            }
+            "--record" => {
+                if let Some(path) = args.next() {
+                    replay_mode = ReplayMode::Record(path.into());
+                }
+            }
+            "--replay" => {
+                if let Some(path) = args.next() {
+                    replay_mode = ReplayMode::Playback(path.into());
+                }
+            }
+            _ => eprintln!("unknown argument: {}", arg),
+        }
+    }
+    if let Some(frames) = headless_frames {
+        headless::run(frames, replay_mode, difficulty);
+        return;
+    }
+
     App::new()
         .add_plugins((
                 DefaultPlugins.set(
@@ -45,27 +86,34 @@ fn main() {
                 collision_core::CollisionCorePlugin,
                 enemy_core::EnemyCorePlugin,
                 explosion_core::ExplosionCorePlugin,
+                game_over::GameOverPlugin,
+                level_complete::LevelCompletePlugin,
+                hud::HudPlugin,
                 level::LevelPlugin,
                 level_background::LevelBackgroundPlugin,
                 basic_enemy::BasicEnemyPlugin, 
                 player::PlayerPlugin, 
                 beam::BeamPlugin,
+                boss::BossPlugin,
+                score::ScorePlugin,
+         ))
+        .add_plugins((
+                ghost_orchid::GhostOrchidPlugin,
+                projectile_pool::ProjectilePoolPlugin,
+                rose_gunner::RoseGunnerPlugin,
+                menu::MenuPlugin,
+                main_menu::MainMenuPlugin,
+                pause_menu::PauseMenuPlugin,
          ))
+        .add_plugins((
+                keymap::KeymapPlugin,
+                player_input::PlayerInputPlugin,
+                replay::ReplayPlugin,
+         ))
+        .insert_resource(replay_mode)
+        .insert_resource(difficulty)
         .init_resource::<Game>()
         .init_state::<GameState>()
         .init_state::<GameLevel>()
         .run();
 }
-
-
-#[derive(Component)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-#[derive(Resource, Default)]
-struct Game {
-    score: i32,
-}
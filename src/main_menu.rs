@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+
+use crate::{level::{Wave, WaveState}, menu::{spawn_menu, MenuAction, MenuEvent, MenuScreen}, player::{Player, PlayerState}, Game, GameLevel, GameState};
+
+pub struct MainMenuPlugin;
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(OnEnter(GameState::MainMenu), open_main_menu)
+        .add_systems(OnExit(GameState::MainMenu), close_main_menu)
+        .add_systems(OnEnter(MenuScreen::Main), spawn_main_menu)
+        .add_systems(OnEnter(MenuScreen::LevelSelect), spawn_level_select)
+        .add_event::<StartRunEvent>()
+        .add_systems(Update, (main_menu_action.run_if(in_state(GameState::MainMenu)), start_run).chain())
+        ;
+    }
+}
+
+fn open_main_menu(mut next_screen: ResMut<NextState<MenuScreen>>) {
+    next_screen.set(MenuScreen::Main);
+}
+
+fn close_main_menu(mut next_screen: ResMut<NextState<MenuScreen>>) {
+    next_screen.set(MenuScreen::None);
+}
+
+fn spawn_main_menu(mut commands: Commands) {
+    spawn_menu(&mut commands, MenuScreen::Main, "FIRST GAME", &[
+        ("Start".to_string(), MenuAction::Start),
+        ("Level Select".to_string(), MenuAction::LevelSelect),
+        ("Settings".to_string(), MenuAction::Settings),
+        ("Quit".to_string(), MenuAction::Quit),
+    ]);
+}
+
+fn spawn_level_select(mut commands: Commands) {
+    let mut items: Vec<_> = GameLevel::ALL.iter()
+        .map(|level| (format!("{:?}", level), MenuAction::StartLevel(*level)))
+        .collect();
+    items.push(("Back".to_string(), MenuAction::Back));
+    spawn_menu(&mut commands, MenuScreen::LevelSelect, "LEVEL SELECT", &items);
+}
+
+// The menu is not part of a run, so its picks are handled straight away rather than
+// going through `PlayerInput` like the pause menu's
+fn main_menu_action(
+    mut menu_events: EventReader<MenuEvent>,
+    mut next_screen: ResMut<NextState<MenuScreen>>,
+    mut start_run_events: EventWriter<StartRunEvent>,
+) {
+    for event in menu_events.read() {
+        match event.0 {
+            MenuAction::Start => {
+                start_run_events.send(StartRunEvent(Some(GameLevel::default())));
+            }
+            MenuAction::LevelSelect => next_screen.set(MenuScreen::LevelSelect),
+            MenuAction::StartLevel(level) => {
+                start_run_events.send(StartRunEvent(Some(level)));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Sent to start a fresh run, from the main menu or after a game over.
+/// Starts on the given level, or on the one the last run started on.
+#[derive(Event)]
+pub struct StartRunEvent(pub Option<GameLevel>);
+
+/// Sets up a fresh run: the player's ship (and with it the canons) and the level's first wave.
+/// Everything changes state together, so nothing from the last run gets a step in between.
+#[allow(clippy::too_many_arguments)]
+fn start_run(
+    mut commands: Commands,
+    mut start_run_events: EventReader<StartRunEvent>,
+    player_query: Query<Entity, With<Player>>,
+    mut game: ResMut<Game>,
+    mut next_level: ResMut<NextState<GameLevel>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut next_player_state: ResMut<NextState<PlayerState>>,
+    mut next_wave: ResMut<NextState<Wave>>,
+    mut next_wave_state: ResMut<NextState<WaveState>>,
+) {
+    let Some(event) = start_run_events.read().last() else {
+        return;
+    };
+    let level = event.0.unwrap_or(game.start_level);
+    // A run can end with the ship still flying, after the last level. It goes now,
+    // before PlayerState::Setup spawns the new one, whichever state changes first
+    for entity in player_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    *game = Game {
+        start_level: level,
+        ..default()
+    };
+    next_level.set(level);
+    next_game_state.set(GameState::Playing);
+    next_player_state.set(PlayerState::Setup);
+    next_wave.set(Wave::One);
+    next_wave_state.set(WaveState::Initialized);
+}
+
+/// Starts a run as soon as the main menu comes up, for when nobody is there to pick Start.
+pub fn skip_main_menu(mut start_run_events: EventWriter<StartRunEvent>) {
+    start_run_events.send(StartRunEvent(None));
+}
@@ -0,0 +1,265 @@
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{keymap::{ActionState, InputAction, Keymap, Rebinding, StartRebindEvent}, GameLevel, GameState};
+
+pub struct MenuPlugin;
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_state::<MenuScreen>()
+        .add_event::<MenuEvent>()
+        .add_systems(OnEnter(MenuScreen::Settings), spawn_settings_menu)
+        .add_systems(
+            Update,
+            (
+                despawn_menus.run_if(state_changed::<MenuScreen>),
+                navigate_menu,
+                highlight_menu,
+                menu_action,
+                update_settings_labels.run_if(resource_changed::<Keymap>.or_else(resource_changed::<Rebinding>)),
+            ).chain()
+        )
+        ;
+    }
+}
+
+/// The menu on screen, if any. Each screen's menu is spawned when it is entered
+/// and despawned when it is left.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Default, States)]
+pub enum MenuScreen {
+    #[default]
+    None,
+    Main,
+    LevelSelect,
+    Pause,
+    Settings,
+}
+
+/// What picking a menu item does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MenuAction {
+    Start,
+    LevelSelect,
+    StartLevel(GameLevel),
+    Resume,
+    RestartWave,
+    Settings,
+    Quit,
+    Back,
+    Rebind(InputAction),
+}
+
+/// Sent when a menu item is picked with the mouse, the keyboard or a gamepad.
+#[derive(Event, Debug)]
+pub struct MenuEvent(pub MenuAction);
+
+/// The root of a menu, its items are the `MenuItem`s below it.
+#[derive(Component)]
+pub struct Menu {
+    screen: MenuScreen,
+    selected: usize,
+}
+
+#[derive(Component)]
+pub struct MenuItem(pub MenuAction);
+
+const MENU_TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const MENU_ITEM_COLOR: Color = Color::rgba(1., 1., 1., 0.1);
+const MENU_SELECTED_COLOR: Color = Color::rgba(1., 1., 1., 0.35);
+
+/// Spawns a centered menu over the game. The first item starts out selected.
+pub fn spawn_menu(commands: &mut Commands, screen: MenuScreen, title: &str, items: &[(String, MenuAction)]) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.),
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.6).into(),
+            z_index: ZIndex::Global(10),
+            ..default()
+        },
+        Menu { screen, selected: 0 },
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            title,
+            TextStyle { font_size: 48., color: MENU_TEXT_COLOR, ..default() },
+        ));
+        for (label, action) in items {
+            parent.spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Px(320.),
+                        padding: UiRect::all(Val::Px(8.)),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    background_color: MENU_ITEM_COLOR.into(),
+                    ..default()
+                },
+                MenuItem(*action),
+            )).with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    label.clone(),
+                    TextStyle { font_size: 24., color: MENU_TEXT_COLOR, ..default() },
+                ));
+            });
+        }
+    });
+}
+
+fn despawn_menus(
+    mut commands: Commands,
+    screen: Res<State<MenuScreen>>,
+    menu_query: Query<(&Menu, Entity)>,
+) {
+    for (menu, entity) in menu_query.iter() {
+        if menu.screen != *screen.get() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Up and down move the selection, hovering an item selects it, and confirming or clicking picks it.
+fn navigate_menu(
+    action_state: Res<ActionState>,
+    rebinding: Res<Rebinding>,
+    mut menu_query: Query<(&mut Menu, &Children)>,
+    item_query: Query<(&MenuItem, Ref<Interaction>)>,
+    mut menu_events: EventWriter<MenuEvent>,
+) {
+    // Every press goes to the binding being picked
+    if rebinding.0.is_some() {
+        return;
+    }
+    for (mut menu, children) in menu_query.iter_mut() {
+        let items: Vec<_> = children.iter().filter_map(|child| item_query.get(*child).ok()).collect();
+        if items.is_empty() {
+            continue;
+        }
+        if action_state.just_pressed(InputAction::MoveDown) {
+            menu.selected = (menu.selected + 1) % items.len();
+        }
+        if action_state.just_pressed(InputAction::MoveUp) {
+            menu.selected = (menu.selected + items.len() - 1) % items.len();
+        }
+        for (index, (item, interaction)) in items.iter().enumerate() {
+            // Only react to the mouse when it does something, so it does not fight the keys
+            if !interaction.is_changed() {
+                continue;
+            }
+            match **interaction {
+                Interaction::Hovered => menu.selected = index,
+                Interaction::Pressed => {
+                    menu.selected = index;
+                    menu_events.send(MenuEvent(item.0));
+                }
+                Interaction::None => {}
+            }
+        }
+        if action_state.just_pressed(InputAction::Confirm) {
+            menu_events.send(MenuEvent(items[menu.selected.min(items.len() - 1)].0.0));
+        }
+    }
+}
+
+fn highlight_menu(
+    menu_query: Query<(&Menu, &Children), Changed<Menu>>,
+    mut item_query: Query<&mut BackgroundColor, With<MenuItem>>,
+) {
+    for (menu, children) in menu_query.iter() {
+        let mut index = 0;
+        for child in children.iter() {
+            if let Ok(mut color) = item_query.get_mut(*child) {
+                color.0 = if index == menu.selected { MENU_SELECTED_COLOR } else { MENU_ITEM_COLOR };
+                index += 1;
+            }
+        }
+    }
+}
+
+// Settings currently only covers key bindings
+const REBINDABLE_ACTIONS: [InputAction; 8] = [
+    InputAction::MoveUp,
+    InputAction::MoveDown,
+    InputAction::MoveLeft,
+    InputAction::MoveRight,
+    InputAction::Fire,
+    InputAction::AltFire,
+    InputAction::Focus,
+    InputAction::Pause,
+];
+
+fn binding_label(action: InputAction, keymap: &Keymap, rebinding: &Rebinding) -> String {
+    if rebinding.0 == Some(action) {
+        return format!("{:?}: press a key or button...", action);
+    }
+    let bindings = keymap.bindings.get(&action)
+        .map(|bindings| bindings.iter().map(|binding| format!("{:?}", binding)).collect::<Vec<_>>().join(", "))
+        .unwrap_or_default();
+    format!("{:?}: {}", action, bindings)
+}
+
+fn spawn_settings_menu(
+    mut commands: Commands,
+    keymap: Res<Keymap>,
+    rebinding: Res<Rebinding>,
+) {
+    let mut items: Vec<_> = REBINDABLE_ACTIONS.iter()
+        .map(|action| (binding_label(*action, &keymap, &rebinding), MenuAction::Rebind(*action)))
+        .collect();
+    items.push(("Back".to_string(), MenuAction::Back));
+    spawn_menu(&mut commands, MenuScreen::Settings, "SETTINGS", &items);
+}
+
+fn update_settings_labels(
+    keymap: Res<Keymap>,
+    rebinding: Res<Rebinding>,
+    item_query: Query<(&MenuItem, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (item, children) in item_query.iter() {
+        let MenuAction::Rebind(action) = item.0 else {
+            continue;
+        };
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                text.sections[0].value = binding_label(action, &keymap, &rebinding);
+            }
+        }
+    }
+}
+
+/// Handles the actions every menu shares. Back returns to the pause menu while
+/// paused, and to the main menu otherwise.
+fn menu_action(
+    mut menu_events: EventReader<MenuEvent>,
+    game_state: Res<State<GameState>>,
+    mut next_screen: ResMut<NextState<MenuScreen>>,
+    mut rebind_events: EventWriter<StartRebindEvent>,
+    mut exit_events: EventWriter<AppExit>,
+) {
+    for event in menu_events.read() {
+        match event.0 {
+            MenuAction::Settings => next_screen.set(MenuScreen::Settings),
+            MenuAction::Rebind(action) => {
+                rebind_events.send(StartRebindEvent(action));
+            }
+            MenuAction::Back => match game_state.get() {
+                GameState::Paused => next_screen.set(MenuScreen::Pause),
+                _ => next_screen.set(MenuScreen::Main),
+            },
+            MenuAction::Quit => {
+                exit_events.send(AppExit);
+            }
+            _ => {}
+        }
+    }
+}
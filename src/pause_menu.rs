@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+
+use crate::{level::WaveState, menu::{spawn_menu, MenuAction, MenuScreen}, player_input::PlayerInput, score::WaveScore, Game, GameState};
+
+pub struct PauseMenuPlugin;
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(OnEnter(GameState::Paused), open_pause_menu)
+        .add_systems(OnExit(GameState::Paused), close_pause_menu)
+        .add_systems(OnEnter(MenuScreen::Pause), spawn_pause_menu)
+        .add_systems(FixedUpdate, (pause_menu_action).run_if(in_state(GameState::Paused)))
+        ;
+    }
+}
+
+fn open_pause_menu(mut next_screen: ResMut<NextState<MenuScreen>>) {
+    next_screen.set(MenuScreen::Pause);
+}
+
+fn close_pause_menu(mut next_screen: ResMut<NextState<MenuScreen>>) {
+    next_screen.set(MenuScreen::None);
+}
+
+fn spawn_pause_menu(mut commands: Commands) {
+    spawn_menu(&mut commands, MenuScreen::Pause, "PAUSED", &[
+        ("Resume".to_string(), MenuAction::Resume),
+        ("Restart Wave".to_string(), MenuAction::RestartWave),
+        ("Settings".to_string(), MenuAction::Settings),
+        ("Quit".to_string(), MenuAction::Quit),
+    ]);
+}
+
+/// Picked actions reach the game through `PlayerInput`, so replays pick them up too.
+fn pause_menu_action(
+    input: Res<PlayerInput>,
+    wave_score: Res<WaveScore>,
+    mut game: ResMut<Game>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut next_wave_state: ResMut<NextState<WaveState>>,
+) {
+    match input.menu {
+        Some(MenuAction::Resume) => next_game_state.set(GameState::Playing),
+        Some(MenuAction::RestartWave) => {
+            // Whatever the wave had scored so far goes with it
+            game.score = wave_score.start_score;
+            next_wave_state.set(WaveState::Initialized);
+            next_game_state.set(GameState::Playing);
+        }
+        _ => {}
+    }
+}
@@ -1,14 +1,15 @@
 
-use bevy::{app::{App, Plugin}, math::bounding::{Aabb2d, BoundingCircle, IntersectsVolume}, prelude::*};
-use bevy::window::PrimaryWindow;
+use bevy::{app::{App, Plugin}, prelude::*};
 use crate::{
-    basic_enemy::EnemyFire, 
-    beam::{Beam, BeamType}, 
-    camera::{MainCamera, CameraShakeEvent}, 
+    beam::{Beam, BeamType, Faction}, 
+    camera::CameraShakeEvent, 
     canon::CanonPlugin, 
-    collision_core::CollisionEvent, 
+    collision_core::{Blinking, BroadphaseSet, CollisionGrid}, 
     explosion_core::ExplosionEvent,
-    GameLevel, 
+    hitbox::Hitbox,
+    projectile_pool::{Pooled, ProjectilePool},
+    player_input::{Aim, PlayerInput},
+    Difficulty,
     GameState
 };
 
@@ -17,14 +18,18 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
        app
-       .add_systems(OnEnter(GameLevel::SpaceOne), setup)
+       .add_event::<ShieldBrokenEvent>()
+       .add_event::<ShieldRestoredEvent>()
+       .init_resource::<ShieldTuning>()
+       .add_systems(OnEnter(PlayerState::Setup), setup)
        .add_systems(OnEnter(PlayerState::Dead), destroy_player)
        .init_state::<PlayerState>()
        .add_plugins(CanonPlugin)
-       .add_systems(Update, (toggle_pause, despawn))
+       .add_systems(FixedUpdate, (despawn).run_if(in_state(PlayerState::Dead)).run_if(not(in_state(GameState::Paused))))
+       .add_systems(FixedUpdate, toggle_pause)
        .add_systems(
-            Update, 
-            (toggle_pause, move_user, check_collision, rotate_user, user_fire_beam)
+            FixedUpdate, 
+            (move_user, tick_invulnerability, check_collision.after(BroadphaseSet), recharge_shield, rotate_user, user_fire_beam)
                 .chain()
                 .run_if(in_state(GameState::Playing))
                 .run_if(in_state(PlayerState::Spawned))
@@ -34,7 +39,9 @@ impl Plugin for PlayerPlugin {
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
 pub enum PlayerState {
+    // No run started yet
     #[default]
+    None,
     Setup,
     Spawned,
     Dead,
@@ -42,10 +49,67 @@ pub enum PlayerState {
 #[derive(Component, Resource)]
 pub struct DespawnTimer(Timer);
 
+pub const PLAYER_MAX_HEALTH: f32 = 100.;
+// ship.png, the hitbox only covers the middle of the hull
+const SHIP_SIZE: Vec2 = Vec2::new(21., 27.);
+const SHIP_HITBOX_FRACTION: f32 = 0.6;
+pub const PLAYER_MAX_SHIELD: f32 = 100.;
+
+/// How the shield comes back after taking hits.
+#[derive(Resource, Clone, Debug)]
+pub struct ShieldTuning {
+    // Seconds without being hit before it starts recharging
+    pub delay: f32,
+    // Shield per second while recharging
+    pub rate: f32,
+    // Recharges up to here, never past PLAYER_MAX_SHIELD
+    pub cap: f32,
+}
+impl Default for ShieldTuning {
+    fn default() -> Self {
+        ShieldTuning {
+            delay: 3.,
+            rate: 25.,
+            cap: PLAYER_MAX_SHIELD,
+        }
+    }
+}
+
+/// The player can not be hurt until this runs out. Given after every hit that
+/// does damage, for as long as the difficulty allows.
+#[derive(Component)]
+pub struct Invulnerable(pub Timer);
+
+impl Difficulty {
+    /// Seconds of invulnerability after taking a hit.
+    pub fn invulnerability(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 2.,
+            Difficulty::Normal => 1.2,
+            Difficulty::Hard => 0.6,
+        }
+    }
+}
+
+/// Sent when a hit takes the last of the player's shield.
+#[derive(Event, Debug)]
+pub struct ShieldBrokenEvent;
+
+/// Sent when a broken shield starts recharging, so it can take hits again.
+#[derive(Event, Debug)]
+pub struct ShieldRestoredEvent;
+
+/// Seconds since the player was last hit, the shield recharges after a while.
+/// Kept off `Player` so ticking it does not count as a change to the player.
+#[derive(Component, Debug, Default)]
+pub struct ShieldRegen {
+    pub since_hit: f32,
+}
+
 #[derive(Component, Debug)]
 pub struct Player {
-    health: f32,
-    shield: f32,
+    pub health: f32,
+    pub shield: f32,
     pub front_weapon: WeaponType,
     pub front_weapon_beam_type: BeamType,
     pub front_weapon_needs_cooldown: bool,
@@ -55,8 +119,8 @@ pub struct Player {
 impl Default for Player {
     fn default() -> Player {
         Player {
-            health: 100.,
-            shield: 100.,
+            health: PLAYER_MAX_HEALTH,
+            shield: PLAYER_MAX_SHIELD,
             front_weapon: WeaponType::WaveGun,
             front_weapon_beam_type: BeamType::Wave,
             front_weapon_needs_cooldown: false,
@@ -89,18 +153,9 @@ impl WaveGun {
     }
 }
 
-#[derive(Component, Debug)]
-struct Velocity {
-    x: f32,
-    y: f32
-}
-
-#[derive(Component, Debug)]
-struct Acceleration {
-    x: f32,
-    y: f32
-}
 pub const USER_SPEED: f32 = 300.0;
+// Held for precise dodging
+pub const USER_FOCUS_SPEED: f32 = 120.0;
 
 fn setup(
     mut commands: Commands, 
@@ -121,78 +176,59 @@ fn setup(
             ..default()
         },
         player,
+        ShieldRegen::default(),
+        Hitbox::capsule_for_sprite(SHIP_SIZE, SHIP_HITBOX_FRACTION),
         weapon,
-        Velocity {x: 0., y: 0.},
-        Acceleration {x: 0., y: 0.},
         FrontWeaponTimer(Timer::from_seconds(weapon_lockout, TimerMode::Once)),
     ));
     player_state.set(PlayerState::Spawned);
 }
 
 fn move_user(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input: Res<PlayerInput>,
     mut query: Query<&mut Transform, With<Player>>,
     time: Res<Time>,
 ) {
     let mut player_transform = query.single_mut();
-    let mut x_direction = 0.0;
-    let mut y_direction = 0.0;
-
-    // Todo: map to an optional user input
-    if keyboard_input.pressed(KeyCode::ArrowLeft) || keyboard_input.pressed(KeyCode::KeyA) {
-        x_direction -= 1.0;
-    }
-    if keyboard_input.pressed(KeyCode::ArrowRight) || keyboard_input.pressed(KeyCode::KeyD) {
-        x_direction += 1.0;
-    }
-    if keyboard_input.pressed(KeyCode::ArrowUp) || keyboard_input.pressed(KeyCode::KeyW) {
-        y_direction += 1.0;
-    }
-    if keyboard_input.pressed(KeyCode::ArrowDown) || keyboard_input.pressed(KeyCode::KeyS) {
-        y_direction -= 1.0;
-    }
+    let speed = if input.focus { USER_FOCUS_SPEED } else { USER_SPEED };
 
     let new_player_position_x =
-        player_transform.translation.x + x_direction * USER_SPEED * time.delta_seconds();
+        player_transform.translation.x + input.movement.x * speed * time.delta_seconds();
     let new_player_position_y =
-        player_transform.translation.y + y_direction * USER_SPEED * time.delta_seconds();
+        player_transform.translation.y + input.movement.y * speed * time.delta_seconds();
 
     player_transform.translation.x = new_player_position_x;
     player_transform.translation.y = new_player_position_y;
 }
 
 fn rotate_user(
+    input: Res<PlayerInput>,
     mut player_query: Query<&mut Transform, With<Player>>,
-    q_windows: Query<&Window, With<PrimaryWindow>>,
-    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
 ) { 
-    let (camera, camera_transform) = q_camera.single();
     let mut player_transform = player_query.single_mut();
     let player_translation = player_transform.translation.xy();
 
-    let cursor_pos = q_windows.single().cursor_position()
-    .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
-    .map(|ray| ray.origin.truncate());
-    match cursor_pos {
-       Some(v) => {
-        let to_mouse = (v - player_translation).normalize();
-        let rotate_to_mouse = Quat::from_rotation_arc(Vec3::Y, to_mouse.extend(0.));
-        player_transform.rotation = rotate_to_mouse;
-       },
-       None => {},
+    let facing = match input.aim {
+        Some(Aim::Point(v)) => Some(v - player_translation),
+        Some(Aim::Direction(direction)) => Some(direction),
+        None => None,
+    };
+    if let Some(facing) = facing.and_then(|facing| facing.try_normalize()) {
+        player_transform.rotation = Quat::from_rotation_arc(Vec3::Y, facing.extend(0.));
     }
 }
 
 fn user_fire_beam(
     mut player_query: Query< (&mut Transform, &mut Player, &mut FrontWeaponTimer), With<Player>>,
-    mouse_buttons:Res<ButtonInput<MouseButton>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input: Res<PlayerInput>,
     asset_server: Res<AssetServer>,
     time: Res<Time>,
+    mut pool: ResMut<ProjectilePool>,
     mut commands: Commands,
 ) {
     let (player_transform, mut player, mut front_weapon_timer) = player_query.single_mut();
-    if front_weapon_timer.0.tick(time.delta()).finished() {
+    // Only written when it flips, so the HUD's Changed<Player> means something changed
+    if front_weapon_timer.0.tick(time.delta()).finished() && player.front_weapon_needs_cooldown {
         player.front_weapon_needs_cooldown = false;
     }
     match player.front_weapon_needs_cooldown {
@@ -202,22 +238,17 @@ fn user_fire_beam(
             let player_angle = player_transform.rotation;
             let axis = (player_angle * Vec3::Y).xy();
             
-            if keyboard_input.pressed(KeyCode::Space) || mouse_buttons.pressed(MouseButton::Left) {
+            if input.fire {
                 let mut spawn_transform = Transform::from_scale(Vec3::splat(1.0));
                 spawn_transform.translation = player_location;
                 spawn_transform.rotation = player_angle;
                 front_weapon_timer.0.reset();
                 player.front_weapon_needs_cooldown = true;
                 let beam_type = &player.front_weapon_beam_type;
-                commands.spawn((
-                    SpriteBundle {
-                        transform: spawn_transform,
-                        texture: asset_server.load("wave.png"),
-                        ..default()
-                    },
+                pool.acquire(&mut commands, &asset_server, beam_type, spawn_transform).insert(
                     //todo: 2 weapons, should be enum w/ params
-                    Beam::new(beam_type, Vec2::new(axis.x, axis.y))
-                ));
+                    Beam::new(beam_type, Vec2::new(axis.x, axis.y)),
+                );
             }
         }
     }
@@ -228,55 +259,71 @@ fn destroy_player(
     mut player_query: Query<(&mut Transform, Entity, &mut Sprite), With<Player>>,
     mut explosion_events: EventWriter<ExplosionEvent>,
 ) {
-    let (transform, mut entity, mut sprite) =  player_query.single_mut();
+    let (transform, entity, mut sprite) =  player_query.single_mut();
     sprite.color = Color::rgba(1., 1., 1., 0.3);
     explosion_events.send(ExplosionEvent(Transform::from_xyz(transform.translation.x, transform.translation.y, 2.)));
-    commands.entity(entity).insert(DespawnTimer(Timer::from_seconds(2.0, TimerMode::Once)));
+    // The hit that did it would otherwise keep the wreck blinking at full colour
+    commands.entity(entity)
+        .remove::<(Invulnerable, Blinking)>()
+        .insert(DespawnTimer(Timer::from_seconds(2.0, TimerMode::Once)));
 }
 
+/// Once the player's wreck has faded out, the run is over.
 fn despawn(
     mut commands: Commands,
-    mut despawn_query: Query<(Entity), With<DespawnTimer>>
+    mut despawn_query: Query<(Entity, &mut DespawnTimer)>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
 ) {
-    for entity in despawn_query.iter_mut() {
-        // commands.entity(entity).despawn_recursive();
+    for (entity, mut despawn_timer) in despawn_query.iter_mut() {
+        if despawn_timer.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            next_game_state.set(GameState::GameOver);
+        }
     }
 }
 
 fn toggle_pause(
     curr_state: Res<State<GameState>>,
     mut next_state: ResMut<NextState<GameState>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-
+    input: Res<PlayerInput>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
-        if curr_state.get() == &GameState::Playing {
-            next_state.set(GameState::Paused)
-        } else {
-            next_state.set(GameState::Playing)
+    if input.pause {
+        match curr_state.get() {
+            GameState::Playing => next_state.set(GameState::Paused),
+            GameState::Paused => next_state.set(GameState::Playing),
+            _ => {}
         }
     }
 }
 
+/// Enemy fire that hits the player is used up, even while invulnerable.
+/// Only the first hit does damage, the rest land during its invulnerability.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn check_collision(
-    commands: Commands,
-    mut player_query: Query<(&Transform, &mut Player, Entity), With<Player>>,
-    mut enemy_fire_query: Query<(&Transform, &mut EnemyFire), With<EnemyFire>>,
-    mut collision_events: EventWriter<CollisionEvent>,
+    mut commands: Commands,
+    mut player_query: Query<(&Transform, &Hitbox, &mut Player, &mut ShieldRegen, Entity, Has<Invulnerable>)>,
+    beam_query: Query<(&Beam, &Transform, &Hitbox, Option<&Pooled>)>,
+    grid: Res<CollisionGrid>,
+    difficulty: Res<Difficulty>,
+    mut pool: ResMut<ProjectilePool>,
     mut camera_shake_events: EventWriter<CameraShakeEvent>,
+    mut shield_broken_events: EventWriter<ShieldBrokenEvent>,
     mut player_state: ResMut<NextState<PlayerState>>
 ) {
-    let (player_transform, mut player, entity) = player_query.single_mut();
-    for (enemy_fire_transform, fire) in enemy_fire_query.iter_mut() {
-        let pcircle = BoundingCircle::new(
-            player_transform.translation.truncate(),
-            20.
-        );
-        let b_box = Aabb2d::new(
-            enemy_fire_transform.translation.truncate(),
-            enemy_fire_transform.scale.truncate() / 2.
-        );
-        if pcircle.intersects(&b_box) {
+    let (player_transform, player_hitbox, mut player, mut shield_regen, entity, mut invulnerable) = player_query.single_mut();
+    for entry in grid.fired_by(Faction::Enemy).query(player_hitbox.bounds(player_transform)) {
+        let Ok((fire, fire_transform, fire_hitbox, pooled)) = beam_query.get(entry.entity) else {
+            continue;
+        };
+        if player_hitbox.intersects(player_transform, fire_hitbox, fire_transform) {
+            pool.release(&mut commands, entry.entity, pooled);
+            if invulnerable {
+                continue;
+            }
+            invulnerable = true;
+            shield_regen.since_hit = 0.;
+            let had_shield = player.shield > 0.;
             //shield should always be taken first;
             if player.shield >= fire.power {
                 player.shield -= fire.power;
@@ -285,14 +332,108 @@ fn check_collision(
                 //need val to roll over into health
                 player.shield = 0.;
                 player.health -= from_health;
-                collision_events.send(CollisionEvent(entity));
                 camera_shake_events.send(CameraShakeEvent);
             } else {
                 player.health -= fire.power;
             }
+            if had_shield && player.shield <= 0. {
+                shield_broken_events.send(ShieldBrokenEvent);
+            }
+            // Blinks for as long as it lasts
+            let seconds = difficulty.invulnerability();
+            commands.entity(entity).insert((
+                Invulnerable(Timer::from_seconds(seconds, TimerMode::Once)),
+                Blinking(Timer::from_seconds(seconds, TimerMode::Once)),
+            ));
         }
     }
     if player.health <= 0. {
         player_state.set(PlayerState::Dead)
     }
-}
\ No newline at end of file
+}
+
+fn tick_invulnerability(
+    mut commands: Commands,
+    mut player_query: Query<(&mut Invulnerable, Entity)>,
+    time: Res<Time>,
+) {
+    for (mut invulnerable, entity) in player_query.iter_mut() {
+        if invulnerable.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+fn recharge_shield(
+    mut player_query: Query<(&mut Player, &mut ShieldRegen)>,
+    tuning: Res<ShieldTuning>,
+    time: Res<Time>,
+    mut shield_restored_events: EventWriter<ShieldRestoredEvent>,
+) {
+    let (mut player, mut shield_regen) = player_query.single_mut();
+    shield_regen.since_hit += time.delta_seconds();
+    let cap = tuning.cap.min(PLAYER_MAX_SHIELD);
+    // Only touches the player when the shield actually goes up
+    if shield_regen.since_hit < tuning.delay || player.shield >= cap {
+        return;
+    }
+    let was_broken = player.shield <= 0.;
+    player.shield = (player.shield + tuning.rate * time.delta_seconds()).min(cap);
+    if was_broken && player.shield > 0. {
+        shield_restored_events.send(ShieldRestoredEvent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::collision_core::{blink, BLINK_DURATION};
+
+    #[test]
+    fn wreck_stays_faded_after_a_fatal_hit() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<ExplosionEvent>>();
+        let player = world.spawn((
+            Player::default(),
+            Transform::default(),
+            Sprite::default(),
+            Invulnerable(Timer::from_seconds(1., TimerMode::Once)),
+            Blinking(Timer::from_seconds(BLINK_DURATION, TimerMode::Once)),
+        )).id();
+        world.run_system_once(destroy_player);
+        world.run_system_once(blink);
+
+        assert_eq!(world.get::<Sprite>(player).unwrap().color, Color::rgba(1., 1., 1., 0.3));
+        assert!(world.get::<Blinking>(player).is_none());
+        assert!(world.get::<Invulnerable>(player).is_none());
+        assert!(world.get::<DespawnTimer>(player).is_some());
+    }
+
+    #[test]
+    fn shield_restored_only_when_coming_back_from_broken() {
+        let mut world = World::new();
+        let mut time = Time::<()>::default();
+        time.advance_by(std::time::Duration::from_millis(100));
+        world.insert_resource(time);
+        world.init_resource::<ShieldTuning>();
+        world.init_resource::<Events<ShieldRestoredEvent>>();
+        let player = world.spawn((
+            Player { shield: 0., ..default() },
+            ShieldRegen { since_hit: 10. },
+        )).id();
+
+        let mut restored = Vec::new();
+        for _ in 0..60 {
+            world.run_system_once(recharge_shield);
+            let mut events = world.resource_mut::<Events<ShieldRestoredEvent>>();
+            restored.push(events.drain().count());
+        }
+
+        assert_eq!(world.get::<Player>(player).unwrap().shield, PLAYER_MAX_SHIELD);
+        assert_eq!(restored.iter().sum::<usize>(), 1);
+        assert_eq!(restored[0], 1);
+    }
+}
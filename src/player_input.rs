@@ -0,0 +1,177 @@
+use bevy::{input::InputSystem, prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{camera::MainCamera, keymap::{ActionSet, ActionState, InputAction}, menu::{MenuAction, MenuEvent}};
+
+pub struct PlayerInputPlugin;
+impl Plugin for PlayerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<PlayerInput>()
+        .init_resource::<PendingPresses>()
+        .init_resource::<InputDevice>()
+        .add_systems(PreUpdate, (track_input_device.after(InputSystem), latch_presses.after(ActionSet)))
+        .add_systems(FixedPreUpdate, read_player_input.in_set(PlayerInputSet))
+        ;
+    }
+}
+
+/// Everything gameplay needs to know about the player's input for one fixed step.
+/// Gameplay systems read this instead of the input devices, so a step can be
+/// fed from a replay just as well as from the keyboard and mouse.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerInput {
+    pub movement: Vec2,
+    pub fire: bool,
+    pub alt_fire: bool,
+    pub focus: bool,
+    pub aim: Option<Aim>,
+    pub pause: bool,
+    pub confirm: bool,
+    // Picked from a menu, e.g. resuming from the pause menu
+    pub menu: Option<MenuAction>,
+}
+
+/// Where the ship should face.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Aim {
+    // A world position, e.g. the mouse cursor
+    Point(Vec2),
+    // A direction from the ship, e.g. the right stick
+    Direction(Vec2),
+}
+
+/// The device the player touched last. Aiming follows the cursor or the right stick depending on it.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputDevice {
+    #[default]
+    KeyboardMouse,
+    Gamepad,
+}
+
+// Stick positions closer to center than this are ignored
+const STICK_DEADZONE: f32 = 0.2;
+
+/// Filled in during `FixedPreUpdate`, before any gameplay system runs.
+#[derive(SystemSet, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct PlayerInputSet;
+
+// Presses only last a single frame, which might not have a fixed step in it,
+// so they are held here until the next step picks them up.
+#[derive(Resource, Default)]
+struct PendingPresses {
+    pause: bool,
+    confirm: bool,
+    menu: Option<MenuAction>,
+}
+
+fn latch_presses(
+    action_state: Res<ActionState>,
+    mut menu_events: EventReader<MenuEvent>,
+    mut pending: ResMut<PendingPresses>,
+) {
+    for event in menu_events.read() {
+        pending.menu = Some(event.0);
+    }
+    if action_state.just_pressed(InputAction::Pause) {
+        pending.pause = true;
+    }
+    if action_state.just_pressed(InputAction::Confirm) {
+        pending.confirm = true;
+    }
+}
+
+fn stick(
+    axes: &Axis<GamepadAxis>,
+    gamepads: &Gamepads,
+    x_axis: GamepadAxisType,
+    y_axis: GamepadAxisType,
+) -> Vec2 {
+    gamepads.iter()
+        .map(|gamepad| Vec2::new(
+            axes.get(GamepadAxis::new(gamepad, x_axis)).unwrap_or(0.),
+            axes.get(GamepadAxis::new(gamepad, y_axis)).unwrap_or(0.),
+        ))
+        .find(|stick| stick.length() > STICK_DEADZONE)
+        .unwrap_or(Vec2::ZERO)
+}
+
+fn track_input_device(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut cursor_moved_events: EventReader<CursorMoved>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    mut device: ResMut<InputDevice>,
+) {
+    let used_keyboard_mouse = keyboard_input.get_just_pressed().next().is_some()
+        || mouse_buttons.get_just_pressed().next().is_some()
+        || cursor_moved_events.read().count() > 0;
+    let used_gamepad = gamepad_buttons.get_just_pressed().next().is_some()
+        || stick(&axes, &gamepads, GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY) != Vec2::ZERO
+        || stick(&axes, &gamepads, GamepadAxisType::RightStickX, GamepadAxisType::RightStickY) != Vec2::ZERO;
+    if used_gamepad {
+        device.set_if_neq(InputDevice::Gamepad);
+    } else if used_keyboard_mouse {
+        device.set_if_neq(InputDevice::KeyboardMouse);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn read_player_input(
+    action_state: Res<ActionState>,
+    device: Res<InputDevice>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut pending: ResMut<PendingPresses>,
+    mut input: ResMut<PlayerInput>,
+) {
+    let mut movement = Vec2::ZERO;
+    if action_state.pressed(InputAction::MoveLeft) {
+        movement.x -= 1.0;
+    }
+    if action_state.pressed(InputAction::MoveRight) {
+        movement.x += 1.0;
+    }
+    if action_state.pressed(InputAction::MoveUp) {
+        movement.y += 1.0;
+    }
+    if action_state.pressed(InputAction::MoveDown) {
+        movement.y -= 1.0;
+    }
+    movement += stick(&axes, &gamepads, GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY).clamp_length_max(1.);
+
+    let aim = match device.as_ref() {
+        InputDevice::KeyboardMouse => {
+            // Without a window (e.g. headless) there is no cursor to aim at
+            match (q_windows.get_single(), q_camera.get_single()) {
+                (Ok(window), Ok((camera, camera_transform))) => window.cursor_position()
+                    .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
+                    .map(|ray| Aim::Point(ray.origin.truncate())),
+                _ => None,
+            }
+        }
+        InputDevice::Gamepad => {
+            // Letting go of the stick keeps the ship facing the same way
+            let right_stick = stick(&axes, &gamepads, GamepadAxisType::RightStickX, GamepadAxisType::RightStickY);
+            (right_stick != Vec2::ZERO).then_some(Aim::Direction(right_stick))
+        }
+    };
+
+    *input = PlayerInput {
+        movement,
+        fire: action_state.pressed(InputAction::Fire),
+        alt_fire: action_state.pressed(InputAction::AltFire),
+        focus: action_state.pressed(InputAction::Focus),
+        aim,
+        pause: pending.pause,
+        confirm: pending.confirm,
+        menu: pending.menu.take(),
+    };
+    pending.pause = false;
+    pending.confirm = false;
+}
@@ -0,0 +1,176 @@
+use bevy::{ecs::system::EntityCommands, prelude::*, utils::{HashMap, HashSet}};
+
+use crate::beam::{Beam, BeamType};
+
+pub struct ProjectilePoolPlugin;
+impl Plugin for ProjectilePoolPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<ProjectilePool>()
+        .add_systems(Startup, prewarm_pool)
+        // Everything released last step has had its components taken off by now
+        .add_systems(FixedFirst, recycle_released)
+        .add_systems(Update, report_pool_pressure)
+        ;
+    }
+}
+
+// Seconds between checks on whether any pool ran dry
+const PRESSURE_REPORT_INTERVAL: f32 = 5.;
+
+/// Hidden projectiles spawned up front for each `BeamType`, so firing does not
+/// spawn and despawn entities. A projectile in use has a `Beam`, released ones
+/// lose it and are hidden until they are handed out again.
+#[derive(Resource, Default)]
+pub struct ProjectilePool {
+    free: HashMap<BeamType, Vec<Entity>>,
+    // Released this step, free to hand out once their commands are applied
+    released: Vec<(BeamType, Entity)>,
+    in_use: HashSet<Entity>,
+    stats: HashMap<BeamType, PoolStats>,
+}
+
+/// How hard one `BeamType`'s pool is being worked.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PoolStats {
+    pub in_use: usize,
+    // Most in use at once
+    pub peak: usize,
+    // Spawned in total, pre-warmed or not
+    pub spawned: usize,
+    // Spawned because the pool ran dry since the last report
+    pub grown: usize,
+}
+
+/// Which pool a projectile belongs to, for as long as it exists.
+#[derive(Component)]
+pub struct Pooled(pub BeamType);
+
+impl BeamType {
+    /// The most expected on screen at once, spawned when the game starts.
+    pub fn pool_size(&self) -> usize {
+        match self {
+            // What bullet hell patterns are made of
+            BeamType::FireBall => 1024,
+            BeamType::Proton => 128,
+            BeamType::Wave => 64,
+            BeamType::PlasmaOrb => 32,
+            BeamType::Laser => 16,
+        }
+    }
+
+    pub fn texture_path(&self) -> &'static str {
+        match self {
+            BeamType::FireBall => "beam_fireball.png",
+            BeamType::Wave => "wave.png",
+            BeamType::PlasmaOrb => "plasma_orb.png",
+            BeamType::Proton | BeamType::Laser => "beam_basic.png",
+        }
+    }
+}
+
+impl ProjectilePool {
+    fn spawn(&mut self, commands: &mut Commands, asset_server: &AssetServer, beam_type: &BeamType) -> Entity {
+        self.stats.entry(beam_type.clone()).or_default().spawned += 1;
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load(beam_type.texture_path()),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            beam_type.hitbox(),
+            Pooled(beam_type.clone()),
+        )).id()
+    }
+
+    /// Shows a projectile of `beam_type` at `transform`, taken from the pool or
+    /// spawned if it ran dry. Insert a `Beam` on it.
+    pub fn acquire<'a>(
+        &mut self,
+        commands: &'a mut Commands,
+        asset_server: &AssetServer,
+        beam_type: &BeamType,
+        transform: Transform,
+    ) -> EntityCommands<'a> {
+        let entity = match self.free.get_mut(beam_type).and_then(|free| free.pop()) {
+            Some(entity) => entity,
+            None => {
+                self.stats.entry(beam_type.clone()).or_default().grown += 1;
+                self.spawn(commands, asset_server, beam_type)
+            }
+        };
+        self.in_use.insert(entity);
+        let stats = self.stats.entry(beam_type.clone()).or_default();
+        stats.in_use += 1;
+        stats.peak = stats.peak.max(stats.in_use);
+        let mut entity_commands = commands.entity(entity);
+        entity_commands.insert((transform, Visibility::Inherited));
+        entity_commands
+    }
+
+    /// Hides a projectile and puts it back in its pool. Releasing one twice in a
+    /// step is fine, so is releasing something that was never pooled, which despawns it.
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity, pooled: Option<&Pooled>) {
+        let Some(Pooled(beam_type)) = pooled else {
+            commands.entity(entity).despawn();
+            return;
+        };
+        if !self.in_use.remove(&entity) {
+            return;
+        }
+        commands.entity(entity)
+            .remove::<Beam>()
+            .insert(Visibility::Hidden);
+        self.released.push((beam_type.clone(), entity));
+        if let Some(stats) = self.stats.get_mut(beam_type) {
+            stats.in_use -= 1;
+        }
+    }
+
+    pub fn stats(&self, beam_type: &BeamType) -> PoolStats {
+        self.stats.get(beam_type).copied().unwrap_or_default()
+    }
+}
+
+fn prewarm_pool(
+    mut pool: ResMut<ProjectilePool>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    for beam_type in BeamType::ALL.iter() {
+        let free: Vec<Entity> = (0..beam_type.pool_size())
+            .map(|_| pool.spawn(&mut commands, &asset_server, beam_type))
+            .collect();
+        pool.free.insert(beam_type.clone(), free);
+    }
+}
+
+fn recycle_released(mut pool: ResMut<ProjectilePool>) {
+    let pool = pool.as_mut();
+    for (beam_type, entity) in pool.released.drain(..) {
+        pool.free.entry(beam_type).or_default().push(entity);
+    }
+}
+
+fn report_pool_pressure(
+    mut pool: ResMut<ProjectilePool>,
+    time: Res<Time>,
+    mut timer: Local<Option<Timer>>,
+) {
+    let timer = timer.get_or_insert_with(|| Timer::from_seconds(PRESSURE_REPORT_INTERVAL, TimerMode::Repeating));
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    for beam_type in BeamType::ALL.iter() {
+        let Some(stats) = pool.stats.get_mut(beam_type) else {
+            continue;
+        };
+        if stats.grown > 0 {
+            warn!(
+                "{:?} pool ran dry and spawned {} more, {} in use at most, {} pre-warmed",
+                beam_type, stats.grown, stats.peak, beam_type.pool_size(),
+            );
+            stats.grown = 0;
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::{fs, path::PathBuf};
+
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{level::{Wave, WaveState}, main_menu::StartRunEvent, player::PlayerState, player_input::{PlayerInput, PlayerInputSet}, Difficulty, Game, GameLevel, GameState};
+
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<ReplayMode>()
+        .add_systems(PreStartup, setup_replay)
+        .add_systems(OnEnter(GameState::MainMenu), skip_menu_for_playback)
+        .add_systems(OnExit(GameState::MainMenu), record_start_level)
+        .add_systems(
+            FixedPreUpdate,
+            (play_back_input, record_input)
+                .chain()
+                .after(PlayerInputSet)
+                // A run starts when the menu is left
+                .run_if(not(in_state(GameState::Loading)))
+                .run_if(not(in_state(GameState::MainMenu)))
+        )
+        .add_systems(FixedLast, apply_fixed_state_transitions)
+        .add_systems(Last, save_recording.run_if(on_event::<AppExit>()))
+        ;
+    }
+}
+
+/// Chosen on the command line with `--record <file>` or `--replay <file>`.
+#[derive(Resource, Clone, Debug, Default)]
+pub enum ReplayMode {
+    #[default]
+    Off,
+    Record(PathBuf),
+    Playback(PathBuf),
+}
+
+/// Every fixed step of player input in a run, plus the seed the run's RNG started from,
+/// the difficulty it was played on and the level it started on.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    // Left out of replays recorded before there were difficulties
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    // Left out of replays recorded before there was a level select
+    #[serde(default)]
+    pub level: GameLevel,
+    pub frames: Vec<PlayerInput>,
+}
+
+/// All gameplay randomness has to come from here, otherwise replays drift.
+#[derive(Resource)]
+pub struct GameRng(pub fastrand::Rng);
+
+#[derive(Resource)]
+struct ReplayRecording(Replay);
+
+#[derive(Resource)]
+struct ReplayPlayback {
+    replay: Replay,
+    index: usize,
+}
+
+fn setup_replay(
+    mut commands: Commands,
+    replay_mode: Res<ReplayMode>,
+    difficulty: Res<Difficulty>,
+    mut game: ResMut<Game>,
+) {
+    let seed = match replay_mode.as_ref() {
+        ReplayMode::Off => fastrand::u64(..),
+        ReplayMode::Record(_) => {
+            let seed = fastrand::u64(..);
+            commands.insert_resource(ReplayRecording(Replay { seed, difficulty: *difficulty, ..default() }));
+            seed
+        }
+        ReplayMode::Playback(path) => {
+            match load_replay(path) {
+                Ok(replay) => {
+                    info!("playing back {} frames from {:?}", replay.frames.len(), path);
+                    let seed = replay.seed;
+                    commands.insert_resource(replay.difficulty);
+                    // A run started without picking a level starts on this one
+                    game.start_level = replay.level;
+                    commands.insert_resource(ReplayPlayback { replay, index: 0 });
+                    seed
+                }
+                Err(err) => {
+                    error!("could not load replay {:?}: {}", path, err);
+                    fastrand::u64(..)
+                }
+            }
+        }
+    };
+    commands.insert_resource(GameRng(fastrand::Rng::with_seed(seed)));
+}
+
+// Rounds of state changes applied after a step, each of which can set off the next
+const MAX_CHAINED_TRANSITIONS: usize = 4;
+
+/// Gameplay changes state during fixed steps (waves, game over, pausing), but the
+/// main schedule only applies state changes once a frame. Applying them after every
+/// step means the next step sees them however many steps the frame runs, so a
+/// replay plays out the same at any frame rate.
+fn apply_fixed_state_transitions(world: &mut World) {
+    // Entering a state can set another right away, e.g. a completed wave queues the next
+    for _ in 0..MAX_CHAINED_TRANSITIONS {
+        world.run_schedule(StateTransition);
+        let pending = transition_pending::<GameState>(world)
+            || transition_pending::<GameLevel>(world)
+            || transition_pending::<Wave>(world)
+            || transition_pending::<WaveState>(world)
+            || transition_pending::<PlayerState>(world);
+        if !pending {
+            break;
+        }
+    }
+}
+
+fn transition_pending<S: States>(world: &World) -> bool {
+    world.get_resource::<NextState<S>>().is_some_and(|next| next.0.is_some())
+}
+
+/// Nobody needs to pick from the menu to play a replay back, it starts on the level it was recorded on.
+fn skip_menu_for_playback(
+    playback: Option<Res<ReplayPlayback>>,
+    mut start_run_events: EventWriter<StartRunEvent>,
+) {
+    if playback.is_some_and(|playback| playback.index == 0) {
+        start_run_events.send(StartRunEvent(None));
+    }
+}
+
+/// The run being recorded has just started, on the level picked in the menu.
+fn record_start_level(
+    game: Res<Game>,
+    recording: Option<ResMut<ReplayRecording>>,
+) {
+    if let Some(mut recording) = recording {
+        // Only the first run counts, restarts start on the same level anyway
+        if recording.0.frames.is_empty() {
+            recording.0.level = game.start_level;
+        }
+    }
+}
+
+fn load_replay(path: &PathBuf) -> Result<Replay, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)?;
+    Ok(ron::from_str(&contents)?)
+}
+
+fn play_back_input(
+    mut input: ResMut<PlayerInput>,
+    playback: Option<ResMut<ReplayPlayback>>,
+) {
+    if let Some(mut playback) = playback {
+        match playback.replay.frames.get(playback.index) {
+            Some(frame) => {
+                *input = frame.clone();
+            }
+            None => {
+                // Hand control back to the live input once the replay runs out
+                if playback.index == playback.replay.frames.len() {
+                    info!("replay finished");
+                }
+            }
+        }
+        playback.index += 1;
+    }
+}
+
+fn record_input(
+    input: Res<PlayerInput>,
+    recording: Option<ResMut<ReplayRecording>>,
+) {
+    if let Some(mut recording) = recording {
+        recording.0.frames.push(input.clone());
+    }
+}
+
+fn save_recording(
+    replay_mode: Res<ReplayMode>,
+    recording: Option<Res<ReplayRecording>>,
+) {
+    if let (ReplayMode::Record(path), Some(recording)) = (replay_mode.as_ref(), recording) {
+        let result = ron::to_string(&recording.0)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| fs::write(path, contents).map_err(|err| err.to_string()));
+        match result {
+            Ok(_) => info!("saved {} frames of replay to {:?}", recording.0.frames.len(), path),
+            Err(err) => error!("could not save replay {:?}: {}", path, err),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+
+use crate::{basic_enemy::{EnemyBehaviorSet, HoldFire}, player::Player};
+
+pub struct RoseGunnerPlugin;
+impl Plugin for RoseGunnerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(FixedUpdate, (aim_rose_gunner, cycle_rose_gunner).chain().in_set(EnemyBehaviorSet))
+        ;
+    }
+}
+
+// Radians per second, slow enough to be outrun
+const TURN_RATE: f32 = 1.5;
+// Seconds spent tracking the player before winding up
+const TRACKING_TIME: f32 = 1.5;
+// Seconds of warning before a burst
+const WIND_UP_TIME: f32 = 0.8;
+// Seconds of firing, volleys come out at the spawn's shoot_interval
+const BURST_TIME: f32 = 0.6;
+const WIND_UP_COLOR: Color = Color::rgb(1., 0.3, 0.3);
+// Flashes per second at the end of the wind-up
+const WIND_UP_FLASH_RATE: f32 = 12.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GunnerStage {
+    Tracking,
+    WindUp,
+    Burst,
+}
+
+/// A RoseGunner turns to face the player, flashes while it winds up, then fires
+/// a burst. It only holds fire, its movement comes from the spawn's move pattern.
+/// Pair it with an aimed shoot type (`AimedBurst` in wave files).
+#[derive(Component, Debug)]
+pub struct RoseGunner {
+    timer: Timer,
+    stage: GunnerStage,
+    // Radians from straight down, the way the sprite faces
+    facing: f32,
+}
+impl Default for RoseGunner {
+    fn default() -> Self {
+        RoseGunner {
+            timer: Timer::from_seconds(TRACKING_TIME, TimerMode::Once),
+            stage: GunnerStage::Tracking,
+            facing: 0.,
+        }
+    }
+}
+
+impl RoseGunner {
+    /// Radians from straight down, what aimed volleys are fired along.
+    pub fn facing(&self) -> f32 {
+        self.facing
+    }
+}
+
+fn aim_rose_gunner(
+    mut query: Query<(&mut RoseGunner, &mut Transform), Without<Player>>,
+    player: Query<&Transform, With<Player>>,
+    time: Res<Time>,
+) {
+    let Ok(player_transform) = player.get_single() else {
+        return;
+    };
+    for (mut gunner, mut transform) in query.iter_mut() {
+        let offset = player_transform.translation.truncate() - transform.translation.truncate();
+        if offset == Vec2::ZERO {
+            continue;
+        }
+        let target = Vec2::NEG_Y.angle_between(offset);
+        // Shortest way round, limited by the turn rate
+        let difference = Vec2::from_angle(gunner.facing).angle_between(Vec2::from_angle(target));
+        let max_turn = TURN_RATE * time.delta_seconds();
+        gunner.facing += difference.clamp(-max_turn, max_turn);
+        transform.rotation = Quat::from_rotation_z(gunner.facing);
+    }
+}
+
+fn cycle_rose_gunner(
+    mut commands: Commands,
+    mut query: Query<(&mut RoseGunner, &mut Sprite, Entity)>,
+    time: Res<Time>,
+) {
+    for (mut gunner, mut sprite, entity) in query.iter_mut() {
+        gunner.timer.tick(time.delta());
+        if gunner.stage == GunnerStage::WindUp {
+            // Reddens, then flashes faster as the burst gets close
+            let t = gunner.timer.fraction();
+            let flash = (gunner.timer.elapsed_secs() * WIND_UP_FLASH_RATE * t).fract() < 0.5;
+            sprite.color = if flash { WIND_UP_COLOR } else { Color::WHITE };
+        }
+        if !gunner.timer.finished() {
+            continue;
+        }
+        match gunner.stage {
+            GunnerStage::Tracking => {
+                gunner.stage = GunnerStage::WindUp;
+                gunner.timer = Timer::from_seconds(WIND_UP_TIME, TimerMode::Once);
+            }
+            GunnerStage::WindUp => {
+                gunner.stage = GunnerStage::Burst;
+                gunner.timer = Timer::from_seconds(BURST_TIME, TimerMode::Once);
+                sprite.color = WIND_UP_COLOR;
+                commands.entity(entity).remove::<HoldFire>();
+            }
+            GunnerStage::Burst => {
+                gunner.stage = GunnerStage::Tracking;
+                gunner.timer = Timer::from_seconds(TRACKING_TIME, TimerMode::Once);
+                sprite.color = Color::WHITE;
+                commands.entity(entity).insert(HoldFire);
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::{prelude::*, time::Stopwatch};
+
+use crate::{basic_enemy::check_collision, enemy_core::EnemyKilledEvent, level::{LevelWavesHandles, Wave, WaveState}, level_waves::LevelWaves, Game, GameLevel, GameState};
+
+pub struct ScorePlugin;
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<WaveScore>()
+        .add_systems(OnEnter(WaveState::Active), start_wave_clock)
+        .add_systems(OnTransition {from: WaveState::Active, to: WaveState::Completed}, award_wave_bonus)
+        .add_systems(
+            FixedUpdate,
+            (tick_wave_clock, award_kill_score)
+                .chain()
+                .after(check_collision)
+                .run_if(in_state(GameState::Playing))
+        )
+        ;
+    }
+}
+
+// Clearing a wave instantly would score this much, it drops to nothing at the wave's bonus_time
+const WAVE_CLEAR_BONUS: f32 = 2000.;
+
+/// How the current wave is going.
+#[derive(Resource, Default, Debug)]
+pub struct WaveScore {
+    pub clock: Stopwatch,
+    pub kills: u32,
+    // The run's score when the wave started
+    pub start_score: i32,
+}
+
+fn start_wave_clock(
+    mut wave_score: ResMut<WaveScore>,
+    game: Res<Game>,
+) {
+    *wave_score = WaveScore {
+        start_score: game.score,
+        ..default()
+    };
+}
+
+fn tick_wave_clock(
+    mut wave_score: ResMut<WaveScore>,
+    time: Res<Time>,
+) {
+    wave_score.clock.tick(time.delta());
+}
+
+fn award_kill_score(
+    mut enemy_killed_events: EventReader<EnemyKilledEvent>,
+    mut wave_score: ResMut<WaveScore>,
+    mut game: ResMut<Game>,
+) {
+    for event in enemy_killed_events.read() {
+        game.score += event.enemy_type.score();
+        wave_score.kills += 1;
+    }
+}
+
+/// Waves where nothing was killed (empty, or every enemy got away) give no bonus.
+fn award_wave_bonus(
+    wave_score: Res<WaveScore>,
+    level_waves_handles: Res<LevelWavesHandles>,
+    level_waves: Res<Assets<LevelWaves>>,
+    level: Res<State<GameLevel>>,
+    wave: Res<State<Wave>>,
+    mut game: ResMut<Game>,
+) {
+    if wave_score.kills == 0 {
+        return;
+    }
+    let Some(definition) = level_waves_handles.get(&level_waves, level.get())
+        .and_then(|level_waves| level_waves.waves.get(wave.get())) else {
+        return;
+    };
+    let remaining = 1. - wave_score.clock.elapsed_secs() / definition.bonus_time;
+    if remaining > 0. {
+        let bonus = (WAVE_CLEAR_BONUS * remaining).round() as i32;
+        info!("{:?} cleared in {:.1}s, bonus {}", wave.get(), wave_score.clock.elapsed_secs(), bonus);
+        game.score += bonus;
+    }
+}
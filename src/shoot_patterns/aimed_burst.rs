@@ -0,0 +1,9 @@
+use crate::{basic_enemy::BEAM_SPEED, beam::{BeamType, ShootPattern}};
+
+/// A tight three shot spread, meant to be fired as `ShootType::Aimed`.
+pub struct AimedBurst;
+impl AimedBurst {
+    pub fn pattern() -> ShootPattern {
+        ShootPattern::arc(&BeamType::FireBall, 3, BEAM_SPEED + 100., 0.3)
+    }
+}
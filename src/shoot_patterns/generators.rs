@@ -0,0 +1,136 @@
+use std::f32::consts::TAU;
+
+use bevy::math::Vec2;
+
+use crate::beam::{Beam, BeamType, ShootPattern, ShootType};
+
+// Evenly spaced steps from 0 to 1, a single step sits in the middle
+fn steps(count: u32) -> impl Iterator<Item = f32> {
+    (0..count).map(move |i| if count > 1 { i as f32 / (count - 1) as f32 } else { 0.5 })
+}
+
+// Straight down, turned by `angle` radians
+fn heading(angle: f32) -> Vec2 {
+    Vec2::from_angle(angle).rotate(Vec2::NEG_Y)
+}
+
+/// Angles are in radians and measured from straight down.
+impl ShootPattern {
+    /// `count` beams evenly spaced all the way round.
+    pub fn ring(beam_type: &BeamType, count: u32, speed: f32) -> ShootPattern {
+        let beam = (0..count)
+            .map(|i| Beam::new(beam_type, heading(TAU * i as f32 / count as f32)).speed(speed))
+            .collect();
+        ShootPattern::new(beam)
+    }
+
+    /// `count` beams spread evenly over `spread`.
+    pub fn arc(beam_type: &BeamType, count: u32, speed: f32, spread: f32) -> ShootPattern {
+        let beam = steps(count)
+            .map(|t| Beam::new(beam_type, heading(spread * (t - 0.5))).speed(speed))
+            .collect();
+        ShootPattern::new(beam)
+    }
+
+    /// A ring that turns by `spin` every volley.
+    pub fn spiral(beam_type: &BeamType, count: u32, speed: f32, spin: f32) -> ShootPattern {
+        ShootPattern::ring(beam_type, count, speed).spin(spin)
+    }
+
+    /// `count` beams heading down, each turned a different random amount within
+    /// `spread` every volley.
+    pub fn random_scatter(beam_type: &BeamType, count: u32, speed: f32, spread: f32) -> ShootPattern {
+        let beam = (0..count)
+            .map(|_| Beam::new(beam_type, Vec2::NEG_Y).speed(speed))
+            .collect();
+        ShootPattern::new(beam).scatter(spread / 2.)
+    }
+
+    /// Directions step evenly from `from` to `to`. They are not normalized, so
+    /// longer ones fly faster.
+    pub fn row(beam_type: &BeamType, count: u32, speed: f32, from: Vec2, to: Vec2) -> ShootPattern {
+        let beam = steps(count)
+            .map(|t| Beam::new(beam_type, from.lerp(to, t)).speed(speed))
+            .collect();
+        ShootPattern::new(beam)
+    }
+
+    /// All in one direction with speeds stepping from `fastest` to `slowest`,
+    /// so the volley strings out into a line. Slower beams live longer, so they
+    /// all get as far before running out.
+    pub fn stream(beam_type: &BeamType, count: u32, direction: Vec2, fastest: f32, slowest: f32) -> ShootPattern {
+        let beam = steps(count)
+            .map(|t| {
+                let speed = fastest + (slowest - fastest) * t;
+                let mut beam = Beam::new(beam_type, direction).speed(speed);
+                if speed > 0. {
+                    beam.lifetime *= fastest / speed;
+                }
+                beam
+            })
+            .collect();
+        ShootPattern::new(beam)
+    }
+}
+
+impl ShootType {
+    /// An arc centred on the player when fired.
+    pub fn aimed_fan(beam_type: &BeamType, count: u32, speed: f32, spread: f32) -> ShootType {
+        ShootType::Aimed(ShootPattern::arc(beam_type, count, speed, spread))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collision_core::ARENA_HALF_SIZE, shoot_patterns::{aimed_burst::AimedBurst, flat_spray::FlatSpray, line_spray::LineSpray}};
+
+    // Each beam's angle from straight down in degrees, how fast it actually moves and how long it lives
+    fn snapshot(mut pattern: ShootPattern) -> Vec<(f32, f32, f32)> {
+        pattern.volley(&mut fastrand::Rng::with_seed(0))
+            .iter()
+            .map(|beam| {
+                assert_eq!(beam.beam_type, BeamType::FireBall);
+                let speed = beam.speed * beam.direction.length();
+                (Vec2::NEG_Y.angle_between(beam.direction).to_degrees(), speed, beam.lifetime)
+            })
+            .collect()
+    }
+
+    fn assert_snapshot(name: &str, pattern: ShootPattern, expected: &[(f32, f32, f32)]) {
+        let actual = snapshot(pattern);
+        assert_eq!(actual.len(), expected.len(), "{} beam count", name);
+        for (i, (&(angle, speed, lifetime), &(expected_angle, expected_speed, expected_lifetime))) in actual.iter().zip(expected).enumerate() {
+            assert!((angle - expected_angle).abs() < 1e-3, "{} beam {} angle {} not {}", name, i, angle, expected_angle);
+            assert!((speed - expected_speed).abs() < 1e-3, "{} beam {} speed {} not {}", name, i, speed, expected_speed);
+            assert!((lifetime - expected_lifetime).abs() < 1e-3, "{} beam {} lifetime {} not {}", name, i, lifetime, expected_lifetime);
+            // Fired from the top of the screen, it still makes it to the bottom
+            assert!(speed * lifetime >= ARENA_HALF_SIZE.y * 2., "{} beam {} runs out after {}", name, i, speed * lifetime);
+        }
+    }
+
+    #[test]
+    fn line_spray() {
+        // Slower beams live longer, so they all get 1000 out
+        assert_snapshot("LineSpray", LineSpray::pattern(), &[
+            (0., 250., 4.), (0., 230., 4.34783), (0., 210., 4.7619), (0., 190., 5.26316),
+            (0., 170., 5.88235), (0., 150., 6.66667), (0., 130., 7.69231), (0., 110., 9.09091),
+            (0., 90., 11.11111), (0., 70., 14.28571), (0., 50., 20.),
+        ]);
+    }
+
+    #[test]
+    fn flat_spray() {
+        assert_snapshot("FlatSpray", FlatSpray::pattern(), &[
+            (45., 282.8427, 4.), (41.98722, 269.07248, 4.), (38.65981, 256.12497, 4.), (34.99202, 244.13112, 4.),
+            (30.96376, 233.23808, 4.), (26.56505, 223.6068, 4.), (21.8014, 215.40659, 4.), (16.69925, 208.80614, 4.),
+            (11.30993, 203.96077, 4.), (5.71062, 200.99751, 4.), (0., 200., 4.),
+        ]);
+    }
+
+    #[test]
+    fn aimed_burst() {
+        assert_snapshot("AimedBurst", AimedBurst::pattern(), &[(-8.59437, 350., 4.), (0., 350., 4.), (8.59437, 350., 4.)]);
+    }
+}
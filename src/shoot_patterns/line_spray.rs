@@ -1,25 +1,11 @@
-use bevy::{math::Vec2, time::{Timer, TimerMode}, };
+use bevy::math::Vec2;
 
-use crate::{basic_enemy::BEAM_SPEED, beam::{Beam, BeamType, ShootPattern}};
+use crate::{basic_enemy::BEAM_SPEED, beam::{BeamType, ShootPattern}};
 
+/// Eleven fireballs straight down, slowing by 20 each so they string out.
 pub struct LineSpray;
 impl LineSpray {
-    pub fn new() -> ShootPattern {
-        ShootPattern { 
-            beam: vec![
-                Beam::new(&BeamType::FireBall, Vec2::new(0., -1.)).speed(BEAM_SPEED),
-                Beam::new(&BeamType::FireBall, Vec2::new(0., -1.)).speed(BEAM_SPEED - 20.),
-                Beam::new(&BeamType::FireBall, Vec2::new(0., -1.)).speed(BEAM_SPEED - 40.),
-                Beam::new(&BeamType::FireBall, Vec2::new(0., -1.)).speed(BEAM_SPEED - 60.),
-                Beam::new(&BeamType::FireBall, Vec2::new(0., -1.)).speed(BEAM_SPEED - 80.),
-                Beam::new(&BeamType::FireBall, Vec2::new(0., -1.)).speed(BEAM_SPEED - 100.),
-                Beam::new(&BeamType::FireBall, Vec2::new(0., -1.)).speed(BEAM_SPEED - 120.),
-                Beam::new(&BeamType::FireBall, Vec2::new(0., -1.)).speed(BEAM_SPEED - 140.),
-                Beam::new(&BeamType::FireBall, Vec2::new(0., -1.)).speed(BEAM_SPEED - 160.),
-                Beam::new(&BeamType::FireBall, Vec2::new(0., -1.)).speed(BEAM_SPEED - 180.),
-                Beam::new(&BeamType::FireBall, Vec2::new(0., -1.)).speed(BEAM_SPEED - 200.),
-            ],
-            timer: Timer::from_seconds(0.25, TimerMode::Repeating)
-        }
+    pub fn pattern() -> ShootPattern {
+        ShootPattern::stream(&BeamType::FireBall, 11, Vec2::new(0., -1.), BEAM_SPEED, BEAM_SPEED - 200.)
     }
-}
\ No newline at end of file
+}
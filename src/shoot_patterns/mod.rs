@@ -1,2 +1,4 @@
+pub mod aimed_burst;
 pub mod flat_spray;
+pub mod generators;
 pub mod line_spray;
\ No newline at end of file